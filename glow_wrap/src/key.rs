@@ -5,6 +5,11 @@ pub trait ConvertKey {
     fn to_key(&self) -> GLuint;
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::{fence_from_sync, sync_from_fence};
+#[cfg(target_arch = "wasm32")]
+pub use web::{fence_from_sync, sync_from_fence};
+
 #[cfg(not(target_arch = "wasm32"))]
 mod native {
     use crate::types::GLuint;
@@ -22,11 +27,22 @@ mod native {
         };
     }
     convert_key!(glow::NativeBuffer);
+    convert_key!(glow::NativeFramebuffer);
     convert_key!(glow::NativeProgram);
+    convert_key!(glow::NativeQuery);
+    convert_key!(glow::NativeSampler);
     convert_key!(glow::NativeShader);
     convert_key!(glow::NativeTexture);
     convert_key!(glow::NativeVertexArray);
 
+    pub fn sync_from_fence(fence: glow::NativeFence) -> crate::types::GLsync {
+        fence.0 as crate::types::GLsync
+    }
+
+    pub fn fence_from_sync(sync: crate::types::GLsync) -> glow::NativeFence {
+        glow::NativeFence(sync as _)
+    }
+
     impl super::ConvertKey for glow::NativeUniformLocation {
         fn from_key(key: crate::types::GLuint) -> Self {
             Self(key)
@@ -55,9 +71,22 @@ mod web {
     }
 
     convert_key!(glow::WebBufferKey);
+    convert_key!(glow::WebFramebufferKey);
     convert_key!(glow::WebProgramKey);
+    convert_key!(glow::WebQueryKey);
+    convert_key!(glow::WebSamplerKey);
     convert_key!(glow::WebShaderKey);
     convert_key!(glow::WebTextureKey);
     convert_key!(glow::WebVertexArrayKey);
     convert_key!(crate::WebGlUniformLocationKey);
+
+    pub fn sync_from_fence(fence: glow::WebFenceKey) -> crate::types::GLsync {
+        fence.data().as_ffi() as GLuint as usize as crate::types::GLsync
+    }
+
+    pub fn fence_from_sync(sync: crate::types::GLsync) -> glow::WebFenceKey {
+        glow::WebFenceKey::from(slotmap::KeyData::from_ffi(
+            (1 as u64) << 32 | sync as usize as u64,
+        ))
+    }
 }
@@ -1,4 +1,5 @@
 use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 use std::sync::Arc;
 use std::sync::OnceLock;
 #[cfg(target_arch = "wasm32")]
@@ -69,17 +70,93 @@ pub mod types {
     pub type GLchar = std::ffi::c_char;
     pub type GLsizeiptr = usize;
     pub type GLbitfield = u32;
+    pub type GLintptr = isize;
+    pub type GLuint64 = u64;
+    pub type GLsync = *const std::ffi::c_void;
+    pub type GLubyte = u8;
+}
+
+// Whether a pixel unpack buffer is bound, making pixel pointers buffer offsets.
+static UNPACK_BUFFER_BOUND: AtomicBool = AtomicBool::new(false);
+// The row length in pixels set with `PixelStorei`, `0` for tightly packed rows.
+static UNPACK_ROW_PIXELS: AtomicI32 = AtomicI32::new(0);
+// The row alignment in bytes set with `PixelStorei`.
+static UNPACK_ROW_ALIGNMENT: AtomicI32 = AtomicI32::new(4);
+// The row alignment in bytes for reading back, set with `PixelStorei`.
+static PACK_ROW_ALIGNMENT: AtomicI32 = AtomicI32::new(4);
+
+// Computes the number of bytes read for an image.
+fn unpack_len(width: i32, height: i32, format: u32, ty: u32) -> usize {
+    let row_length = match UNPACK_ROW_PIXELS.load(Ordering::Relaxed) {
+        0 => width,
+        n => n,
+    };
+    let alignment = UNPACK_ROW_ALIGNMENT.load(Ordering::Relaxed);
+    image_len(width, height, row_length, alignment, format, ty)
+}
+
+// Computes the number of bytes written when reading back an image.
+fn pack_len(width: i32, height: i32, format: u32, ty: u32) -> usize {
+    let alignment = PACK_ROW_ALIGNMENT.load(Ordering::Relaxed);
+    image_len(width, height, width, alignment, format, ty)
+}
+
+fn image_len(
+    width: i32,
+    height: i32,
+    row_length: i32,
+    alignment: i32,
+    format: u32,
+    ty: u32,
+) -> usize {
+    let channels = match format {
+        glow::RED => 1,
+        glow::RG => 2,
+        glow::RGB => 3,
+        _ => 4,
+    };
+    let size = match ty {
+        glow::UNSIGNED_SHORT | glow::HALF_FLOAT => 2,
+        glow::FLOAT => 4,
+        _ => 1,
+    };
+    if height == 0 {
+        return 0;
+    }
+    let stride = (row_length * channels * size + alignment - 1) / alignment * alignment;
+    (stride * (height - 1) + width * channels * size) as usize
+}
+
+unsafe fn unpack_data<'a>(pixels: *const u8, len: usize) -> glow::PixelUnpackData<'a> {
+    if UNPACK_BUFFER_BOUND.load(Ordering::Relaxed) {
+        glow::PixelUnpackData::BufferOffset(pixels as usize as u32)
+    } else if pixels.is_null() {
+        glow::PixelUnpackData::Slice(None)
+    } else {
+        glow::PixelUnpackData::Slice(Some(std::slice::from_raw_parts(pixels, len)))
+    }
 }
 
 pub use glow::{
-    ARRAY_BUFFER, BLEND, CLAMP_TO_BORDER, CLAMP_TO_EDGE, COLOR_BUFFER_BIT, COMPILE_STATUS,
-    CONSTANT_COLOR, CULL_FACE, DEPTH_BUFFER_BIT, DST_ALPHA, DST_COLOR, DYNAMIC_DRAW, EQUAL, FALSE,
-    FLOAT, FRAGMENT_SHADER, FRAMEBUFFER_SRGB, FUNC_ADD, FUNC_SUBTRACT, INCR, KEEP, LINEAR,
-    LINEAR_MIPMAP_LINEAR, LINEAR_MIPMAP_NEAREST, MIRRORED_REPEAT, NEAREST, NEAREST_MIPMAP_LINEAR,
-    NEAREST_MIPMAP_NEAREST, NEVER, NOTEQUAL, ONE, ONE_MINUS_SRC_ALPHA, REPEAT, REPLACE, RGBA,
-    SCISSOR_TEST, SRC_ALPHA, SRC_COLOR, SRGB_ALPHA, STENCIL_BUFFER_BIT, STENCIL_TEST, TEXTURE_2D,
-    TEXTURE_BORDER_COLOR, TEXTURE_MAG_FILTER, TEXTURE_MIN_FILTER, TEXTURE_WRAP_S, TEXTURE_WRAP_T,
-    TRIANGLES, TRUE, UNSIGNED_BYTE, VERTEX_SHADER, ZERO,
+    ALREADY_SIGNALED, ARRAY_BUFFER, BGRA, BLEND, BUFFER, CLAMP_TO_BORDER, CLAMP_TO_EDGE,
+    COLOR_ATTACHMENT0, COLOR_BUFFER_BIT, COMPILE_STATUS, COMPRESSED_TEXTURE_FORMATS,
+    CONDITION_SATISFIED, CONSTANT_COLOR, CONTEXT_LOST, CULL_FACE, DEBUG_SEVERITY_HIGH,
+    DEBUG_SEVERITY_LOW, DEBUG_SEVERITY_MEDIUM, DEPTH_BUFFER_BIT, DST_ALPHA, DST_COLOR,
+    DYNAMIC_DRAW, EQUAL, EXTENSIONS, FALSE, FLOAT, FRAGMENT_SHADER, FRAMEBUFFER,
+    FRAMEBUFFER_BINDING, FRAMEBUFFER_COMPLETE, FRAMEBUFFER_SRGB, FUNC_ADD, FUNC_SUBTRACT, GREEN,
+    HALF_FLOAT, INCR, KEEP, LINEAR, LINEAR_MIPMAP_LINEAR, LINEAR_MIPMAP_NEAREST, LINK_STATUS,
+    MAP_INVALIDATE_BUFFER_BIT, MAP_WRITE_BIT, MAX_TEXTURE_SIZE, MIRRORED_REPEAT, NEAREST,
+    NEAREST_MIPMAP_LINEAR, NEAREST_MIPMAP_NEAREST, NEVER, NOTEQUAL, NO_ERROR,
+    NUM_COMPRESSED_TEXTURE_FORMATS, NUM_EXTENSIONS, ONE, ONE_MINUS_SRC_ALPHA, PACK_ALIGNMENT,
+    PIXEL_UNPACK_BUFFER, PROGRAM, QUERY_RESULT, QUERY_RESULT_AVAILABLE, R32F, R8, RED, REPEAT,
+    REPLACE, RG, RG8, RGB, RGB8, RGBA, RGBA16, RGBA16F, RGBA32F, SAMPLER, SCISSOR_TEST, SRC_ALPHA,
+    SRC_COLOR, SRGB8, SRGB_ALPHA, STENCIL_BUFFER_BIT, STENCIL_TEST, STREAM_DRAW,
+    SYNC_FLUSH_COMMANDS_BIT, SYNC_GPU_COMMANDS_COMPLETE, TEXTURE, TEXTURE0, TEXTURE1, TEXTURE2,
+    TEXTURE_2D, TEXTURE_BORDER_COLOR, TEXTURE_LOD_BIAS, TEXTURE_MAG_FILTER, TEXTURE_MAX_LEVEL,
+    TEXTURE_MIN_FILTER, TEXTURE_SWIZZLE_A, TEXTURE_SWIZZLE_B, TEXTURE_SWIZZLE_G, TEXTURE_SWIZZLE_R,
+    TEXTURE_WRAP_S, TEXTURE_WRAP_T, TIMEOUT_EXPIRED, TIME_ELAPSED, TRIANGLES, TRUE,
+    UNPACK_ALIGNMENT, UNPACK_ROW_LENGTH, UNSIGNED_BYTE, UNSIGNED_SHORT, VERTEX_ARRAY,
+    VERTEX_SHADER, WAIT_FAILED, ZERO,
 };
 
 #[allow(non_snake_case)]
@@ -90,6 +167,129 @@ pub unsafe fn DeleteTextures(n: types::GLsizei, textures: *const types::GLuint)
     }
 }
 
+// glow can not query the size of a texture to bound the output,
+// so reading back goes through a framebuffer, see `GetTexImage::is_loaded`.
+#[allow(non_snake_case)]
+pub unsafe fn GetTexImage(_target: u32, _level: i32, _format: u32, _ty: u32, _pixels: *mut u8) {
+    unreachable!("`GetTexImage` is not loaded");
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn ReadPixels(
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    format: u32,
+    ty: u32,
+    pixels: *mut u8,
+) {
+    let len = pack_len(width, height, format, ty);
+    let pixels = std::slice::from_raw_parts_mut(pixels, len);
+    gl().read_pixels(
+        x,
+        y,
+        width,
+        height,
+        format,
+        ty,
+        glow::PixelPackData::Slice(Some(pixels)),
+    );
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GenFramebuffers(_: types::GLsizei, framebuffers: *mut types::GLuint) {
+    if let Ok(id) = gl().create_framebuffer() {
+        *framebuffers = glow::Framebuffer::to_key(&id);
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn DeleteFramebuffers(n: types::GLsizei, framebuffers: *const types::GLuint) {
+    let framebuffers = std::slice::from_raw_parts(framebuffers, n as usize);
+    for &framebuffer in framebuffers {
+        if framebuffer != 0 {
+            gl().delete_framebuffer(glow::Framebuffer::from_key(framebuffer));
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn BindFramebuffer(target: u32, framebuffer: types::GLuint) {
+    let framebuffer = match framebuffer {
+        0 => None,
+        id => Some(glow::Framebuffer::from_key(id)),
+    };
+    gl().bind_framebuffer(target, framebuffer);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn FramebufferTexture2D(
+    target: u32,
+    attachment: u32,
+    texture_target: u32,
+    texture: types::GLuint,
+    level: i32,
+) {
+    let texture = match texture {
+        0 => None,
+        id => Some(glow::Texture::from_key(id)),
+    };
+    gl().framebuffer_texture_2d(target, attachment, texture_target, texture, level);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn CheckFramebufferStatus(target: u32) -> types::GLenum {
+    gl().check_framebuffer_status(target)
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GenSamplers(_: types::GLsizei, samplers: *mut types::GLuint) {
+    if let Ok(id) = gl().create_sampler() {
+        *samplers = glow::Sampler::to_key(&id);
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn DeleteSamplers(n: types::GLsizei, samplers: *const types::GLuint) {
+    let samplers = std::slice::from_raw_parts(samplers, n as usize);
+    for &sampler in samplers {
+        if sampler != 0 {
+            gl().delete_sampler(glow::Sampler::from_key(sampler));
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn BindSampler(unit: types::GLuint, sampler: types::GLuint) {
+    let sampler = match sampler {
+        0 => None,
+        id => Some(glow::Sampler::from_key(id)),
+    };
+    gl().bind_sampler(unit, sampler);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn SamplerParameteri(sampler: types::GLuint, parameter: u32, value: i32) {
+    gl().sampler_parameter_i32(glow::Sampler::from_key(sampler), parameter, value);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn SamplerParameterf(sampler: types::GLuint, parameter: u32, value: f32) {
+    gl().sampler_parameter_f32(glow::Sampler::from_key(sampler), parameter, value);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn SamplerParameterfv(sampler: types::GLuint, parameter: u32, values: *const f32) {
+    let values = std::slice::from_raw_parts(values, 4);
+    gl().sampler_parameter_f32_slice(glow::Sampler::from_key(sampler), parameter, values);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetFloatv(parameter: types::GLenum, data: *mut f32) {
+    *data = gl().get_parameter_f32(parameter);
+}
+
 #[allow(non_snake_case)]
 pub unsafe fn GenTextures(_: types::GLsizei, textures: *mut types::GLuint) {
     if let Ok(id) = gl().create_texture() {
@@ -107,6 +307,11 @@ pub unsafe fn TexParameteri(target: u32, parameter: u32, value: i32) {
     gl().tex_parameter_i32(target, parameter, value);
 }
 
+#[allow(non_snake_case)]
+pub unsafe fn TexParameterf(target: u32, parameter: u32, value: f32) {
+    gl().tex_parameter_f32(target, parameter, value);
+}
+
 #[allow(non_snake_case)]
 pub unsafe fn TexParameterfv(target: u32, parameter: u32, values: *const f32) {
     let values = std::slice::from_raw_parts(values, 4);
@@ -130,8 +335,7 @@ pub unsafe fn TexImage2D(
     ty: u32,
     pixels: *const u8,
 ) {
-    debug_assert!(format == glow::SRGB_ALPHA || format == glow::RGBA);
-    let pixels = std::slice::from_raw_parts(pixels, (width * height * 4) as usize);
+    let pixels = unpack_data(pixels, unpack_len(width, height, format, ty));
     gl().tex_image_2d(
         target,
         level,
@@ -141,7 +345,7 @@ pub unsafe fn TexImage2D(
         border,
         format,
         ty,
-        glow::PixelUnpackData::Slice(Some(pixels)),
+        pixels,
     );
 }
 
@@ -157,21 +361,100 @@ pub unsafe fn TexSubImage2D(
     ty: u32,
     pixels: *const u8,
 ) {
-    debug_assert!(format == glow::SRGB_ALPHA || format == glow::RGBA);
-    let pixels = std::slice::from_raw_parts(pixels, (width * height * 4) as usize);
+    let pixels = unpack_data(pixels, unpack_len(width, height, format, ty));
     gl().tex_sub_image_2d(
+        target, level, x_offset, y_offset, width, height, format, ty, pixels,
+    );
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn CompressedTexImage2D(
+    target: u32,
+    level: i32,
+    internal_format: u32,
+    width: i32,
+    height: i32,
+    border: i32,
+    image_size: i32,
+    data: *const u8,
+) {
+    let data = std::slice::from_raw_parts(data, image_size as usize);
+    gl().compressed_tex_image_2d(
         target,
         level,
-        x_offset,
-        y_offset,
+        internal_format as i32,
         width,
         height,
-        format,
-        ty,
-        glow::PixelUnpackData::Slice(Some(pixels)),
+        border,
+        image_size,
+        data,
     );
 }
 
+// The extension names of the context, kept alive for `GetStringi`.
+static EXTENSION_NAMES: OnceLock<Vec<std::ffi::CString>> = OnceLock::new();
+
+fn extensions() -> &'static [std::ffi::CString] {
+    EXTENSION_NAMES.get_or_init(|| {
+        let mut names: Vec<_> = gl()
+            .supported_extensions()
+            .iter()
+            .filter_map(|name| std::ffi::CString::new(name.as_str()).ok())
+            .collect();
+        names.sort();
+        names
+    })
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetIntegerv(parameter: types::GLenum, data: *mut types::GLint) {
+    match parameter {
+        glow::NUM_EXTENSIONS => *data = extensions().len() as types::GLint,
+        glow::COMPRESSED_TEXTURE_FORMATS => {
+            let count = gl().get_parameter_i32(glow::NUM_COMPRESSED_TEXTURE_FORMATS);
+            let data = std::slice::from_raw_parts_mut(data, count as usize);
+            gl().get_parameter_i32_slice(parameter, data);
+        }
+        glow::FRAMEBUFFER_BINDING => {
+            *data = match gl().get_parameter_framebuffer(parameter) {
+                Some(id) => glow::Framebuffer::to_key(&id) as types::GLint,
+                None => 0,
+            }
+        }
+        // WebGL returns a boolean, which does not convert to an integer.
+        GPU_DISJOINT_EXT => *data = gl().get_parameter_bool(parameter) as types::GLint,
+        _ => *data = gl().get_parameter_i32(parameter),
+    }
+}
+
+// Set after timer queries were disturbed, for example by a change of the GPU clock.
+const GPU_DISJOINT_EXT: u32 = 0x8FBB;
+
+#[allow(non_snake_case)]
+pub unsafe fn GetStringi(name: types::GLenum, index: types::GLuint) -> *const types::GLubyte {
+    debug_assert!(name == glow::EXTENSIONS);
+    match extensions().get(index as usize) {
+        Some(name) => name.as_ptr() as *const types::GLubyte,
+        None => std::ptr::null(),
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn ActiveTexture(unit: types::GLenum) {
+    gl().active_texture(unit);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn PixelStorei(parameter: types::GLenum, value: types::GLint) {
+    match parameter {
+        glow::UNPACK_ROW_LENGTH => UNPACK_ROW_PIXELS.store(value, Ordering::Relaxed),
+        glow::UNPACK_ALIGNMENT => UNPACK_ROW_ALIGNMENT.store(value, Ordering::Relaxed),
+        glow::PACK_ALIGNMENT => PACK_ROW_ALIGNMENT.store(value, Ordering::Relaxed),
+        _ => {}
+    }
+    gl().pixel_store_i32(parameter, value);
+}
+
 #[allow(non_snake_case)]
 pub unsafe fn GetUniformLocation(
     program: types::GLuint,
@@ -313,6 +596,33 @@ pub unsafe fn Uniform4f(location: types::GLint, x: f32, y: f32, z: f32, w: f32)
     });
 }
 
+#[allow(non_snake_case)]
+pub unsafe fn Uniform3f(location: types::GLint, x: f32, y: f32, z: f32) {
+    get_uniform(location as u32, |location| {
+        gl().uniform_3_f32(Some(&location), x, y, z);
+    });
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn Uniform1i(location: types::GLint, value: i32) {
+    get_uniform(location as u32, |location| {
+        gl().uniform_1_i32(Some(&location), value);
+    });
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn UniformMatrix3fv(
+    location: types::GLint,
+    count: types::GLint,
+    transpose: types::GLboolean,
+    value: *const f32,
+) {
+    let value = std::slice::from_raw_parts(value, count as usize * 9);
+    get_uniform(location as u32, |location| {
+        gl().uniform_matrix_3_f32_slice(Some(&location), transpose == TRUE, value);
+    });
+}
+
 #[allow(non_snake_case)]
 pub unsafe fn ProgramUniformMatrix2fv(
     program: types::GLuint,
@@ -394,6 +704,13 @@ pub unsafe fn DeleteVertexArrays(_: types::GLsizei, array: *const types::GLuint)
 
 #[allow(non_snake_case)]
 pub unsafe fn BindBuffer(target: types::GLenum, buffer: types::GLuint) {
+    if target == glow::PIXEL_UNPACK_BUFFER {
+        UNPACK_BUFFER_BOUND.store(buffer != 0, Ordering::Relaxed);
+    }
+    if buffer == 0 {
+        gl().bind_buffer(target, None);
+        return;
+    }
     let buffer = glow::Buffer::from_key(buffer);
     gl().bind_buffer(target, Some(buffer));
 }
@@ -441,11 +758,65 @@ pub unsafe fn BufferData(
     data: *const f64,
     usage: types::GLenum,
 ) {
+    if data.is_null() {
+        gl().buffer_data_size(target, size as i32, usage);
+        return;
+    }
     let data: *const u8 = std::mem::transmute(data);
     let data = std::slice::from_raw_parts(data, size);
     gl().buffer_data_u8_slice(target, data, usage);
 }
 
+#[allow(non_snake_case)]
+pub unsafe fn BufferSubData(
+    target: types::GLenum,
+    offset: types::GLintptr,
+    size: types::GLsizeiptr,
+    data: *const std::ffi::c_void,
+) {
+    let data = std::slice::from_raw_parts(data as *const u8, size);
+    gl().buffer_sub_data_u8_slice(target, offset as i32, data);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn MapBufferRange(
+    target: types::GLenum,
+    offset: types::GLintptr,
+    length: types::GLsizeiptr,
+    access: types::GLbitfield,
+) -> *mut std::ffi::c_void {
+    gl().map_buffer_range(target, offset as i32, length as i32, access) as *mut _
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn UnmapBuffer(target: types::GLenum) -> types::GLboolean {
+    gl().unmap_buffer(target);
+    TRUE
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn FenceSync(condition: types::GLenum, flags: types::GLbitfield) -> types::GLsync {
+    match gl().fence_sync(condition, flags) {
+        Ok(fence) => key::sync_from_fence(fence),
+        Err(_) => std::ptr::null(),
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn ClientWaitSync(
+    sync: types::GLsync,
+    flags: types::GLbitfield,
+    timeout: types::GLuint64,
+) -> types::GLenum {
+    let timeout = timeout.min(i32::MAX as u64) as i32;
+    gl().client_wait_sync(key::fence_from_sync(sync), flags, timeout)
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn DeleteSync(sync: types::GLsync) {
+    gl().delete_sync(key::fence_from_sync(sync));
+}
+
 #[allow(non_snake_case)]
 pub unsafe fn CreateShader(shader_type: types::GLenum) -> types::GLuint {
     if let Ok(shader) = gl().create_shader(shader_type) {
@@ -486,6 +857,23 @@ pub unsafe fn GetShaderInfoLog(shader: types::GLuint) -> String {
     log
 }
 
+#[allow(non_snake_case)]
+pub unsafe fn GetProgramiv(
+    program: types::GLuint,
+    parameter: types::GLenum,
+    params: *mut types::GLint,
+) {
+    debug_assert!(parameter == glow::LINK_STATUS);
+    let program = glow::Program::from_key(program);
+    *params = gl().get_program_link_status(program) as types::GLint;
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetProgramInfoLog(program: types::GLuint) -> String {
+    let program = glow::Program::from_key(program);
+    gl().get_program_info_log(program)
+}
+
 #[allow(non_snake_case)]
 pub unsafe fn AttachShader(program: types::GLuint, shader: types::GLuint) {
     let program = glow::Program::from_key(program);
@@ -615,14 +1003,157 @@ pub unsafe fn BlendFuncSeparate(
     gl().blend_func_separate(sfactorRGB, dfactorRGB, sfactorAlpha, dfactorAlpha);
 }
 
+#[allow(non_snake_case)]
+pub unsafe fn GenQueries(n: types::GLsizei, ids: *mut types::GLuint) {
+    let ids = std::slice::from_raw_parts_mut(ids, n as usize);
+    for id in ids {
+        if let Ok(query) = gl().create_query() {
+            *id = glow::Query::to_key(&query);
+        }
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn DeleteQueries(n: types::GLsizei, ids: *const types::GLuint) {
+    let ids = std::slice::from_raw_parts(ids, n as usize);
+    for id in ids {
+        gl().delete_query(glow::Query::from_key(*id));
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn BeginQuery(target: types::GLenum, id: types::GLuint) {
+    gl().begin_query(target, glow::Query::from_key(id));
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn EndQuery(target: types::GLenum) {
+    gl().end_query(target);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetQueryObjectiv(
+    id: types::GLuint,
+    parameter: types::GLenum,
+    params: *mut types::GLint,
+) {
+    *params = gl().get_query_parameter_u32(glow::Query::from_key(id), parameter) as types::GLint;
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetQueryObjectui64v(id: types::GLuint, parameter: types::GLenum, params: *mut u64) {
+    // glow only reads 32 bit results, which covers about 4 seconds in nanoseconds.
+    *params = gl().get_query_parameter_u32(glow::Query::from_key(id), parameter) as u64;
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetError() -> types::GLenum {
+    let code = gl().get_error();
+    if code == glow::CONTEXT_LOST || code == CONTEXT_LOST_WEBGL {
+        CONTEXT_LOST_FLAG.store(true, Ordering::Relaxed);
+    }
+    code
+}
+
+// Error code reported by WebGL after a `webglcontextlost` event.
+const CONTEXT_LOST_WEBGL: u32 = 0x9242;
+
+// Whether a context loss was reported by `GetError` or `set_context_lost`.
+static CONTEXT_LOST_FLAG: AtomicBool = AtomicBool::new(false);
+
+/// Marks the context as lost or restored.
+///
+/// glow has no robustness query, so call this from a `webglcontextlost` handler
+/// to report the loss through `GetGraphicsResetStatus`.
+/// Losses seen by `GetError` are marked automatically.
+pub fn set_context_lost(lost: bool) {
+    CONTEXT_LOST_FLAG.store(lost, Ordering::Relaxed);
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn GetGraphicsResetStatus() -> types::GLenum {
+    // Reading the error flag here would drop unrelated errors.
+    if CONTEXT_LOST_FLAG.load(Ordering::Relaxed) {
+        glow::UNKNOWN_CONTEXT_RESET
+    } else {
+        glow::NO_ERROR
+    }
+}
+
+#[allow(non_snake_case)]
+pub unsafe fn ObjectLabel(
+    identifier: types::GLenum,
+    name: types::GLuint,
+    length: types::GLsizei,
+    label: *const types::GLchar,
+) {
+    // WebGL does not support `KHR_debug`.
+    if !gl().supports_debug() {
+        return;
+    }
+    let label = std::slice::from_raw_parts(label as *const u8, length as usize);
+    let label = std::str::from_utf8(label).unwrap_or("");
+    gl().object_label(identifier, name, Some(label));
+}
+
 #[inline]
 unsafe fn char_ptr_to_str<'a>(ptr: *const types::GLchar) -> &'a str {
     CStr::from_ptr(ptr).to_str().unwrap_or("")
 }
 
+#[allow(non_snake_case)]
+pub mod CreateShader {
+    pub fn is_loaded() -> bool {
+        super::CONTEXT.get().is_some()
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod GenTextures {
+    pub fn is_loaded() -> bool {
+        super::CONTEXT.get().is_some()
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod GenQueries {
+    pub fn is_loaded() -> bool {
+        super::CONTEXT.get().is_some()
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod GetTexImage {
+    pub fn is_loaded() -> bool {
+        false
+    }
+}
+
 #[allow(non_snake_case)]
 pub mod Enable {
     pub fn is_loaded() -> bool {
         true
     }
 }
+
+#[allow(non_snake_case)]
+pub mod GetGraphicsResetStatus {
+    pub fn is_loaded() -> bool {
+        true
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod MapBufferRange {
+    pub fn is_loaded() -> bool {
+        // WebGL does not support mapping buffers.
+        cfg!(not(target_arch = "wasm32"))
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod ObjectLabel {
+    pub fn is_loaded() -> bool {
+        true
+    }
+}
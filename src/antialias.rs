@@ -0,0 +1,97 @@
+//! Feathered edges for anti-aliased shapes without MSAA.
+
+use fnv::FnvHashMap;
+
+type Point = [u32; 2];
+
+// Compares positions by bits, since shared vertices are copies of the same value.
+fn edge_key(a: [f32; 2], b: [f32; 2]) -> (Point, Point) {
+    let a = [a[0].to_bits(), a[1].to_bits()];
+    let b = [b[0].to_bits(), b[1].to_bits()];
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Appends feather quads along the outer edges of a triangle list.
+///
+/// Edges shared by two triangles are inside the shape and get no feather.
+/// The feather fades from the edge color to transparent over `width`,
+/// given in normalized device coordinates per axis.
+pub(crate) fn feather_edges(
+    positions: &mut Vec<[f32; 2]>,
+    colors: &mut Vec<[f32; 4]>,
+    width: [f32; 2],
+) {
+    let triangles = positions.len() / 3;
+    let mut edges: FnvHashMap<_, u32> = FnvHashMap::default();
+    for tri in positions.chunks_exact(3) {
+        for i in 0..3 {
+            *edges.entry(edge_key(tri[i], tri[(i + 1) % 3])).or_insert(0) += 1;
+        }
+    }
+
+    for t in 0..triangles {
+        for i in 0..3 {
+            let (ia, ib, ic) = (3 * t + i, 3 * t + (i + 1) % 3, 3 * t + (i + 2) % 3);
+            let (a, b, c) = (positions[ia], positions[ib], positions[ic]);
+            if edges[&edge_key(a, b)] != 1 {
+                continue;
+            }
+
+            // Compute the outward normal in feather units,
+            // so the feather has the same width in all directions.
+            let d = [(b[0] - a[0]) / width[0], (b[1] - a[1]) / width[1]];
+            let len = (d[0] * d[0] + d[1] * d[1]).sqrt();
+            if len == 0.0 {
+                continue;
+            }
+            let mut n = [-d[1] / len, d[0] / len];
+            let ac = [(c[0] - a[0]) / width[0], (c[1] - a[1]) / width[1]];
+            if n[0] * ac[0] + n[1] * ac[1] > 0.0 {
+                n = [-n[0], -n[1]];
+            }
+            let offset = [n[0] * width[0], n[1] * width[1]];
+            let a_out = [a[0] + offset[0], a[1] + offset[1]];
+            let b_out = [b[0] + offset[0], b[1] + offset[1]];
+
+            let (color_a, color_b) = (colors[ia], colors[ib]);
+            let color_a_out = [color_a[0], color_a[1], color_a[2], 0.0];
+            let color_b_out = [color_b[0], color_b[1], color_b[2], 0.0];
+
+            positions.extend_from_slice(&[a, b, b_out, a, b_out, a_out]);
+            colors.extend_from_slice(&[
+                color_a,
+                color_b,
+                color_b_out,
+                color_a,
+                color_b_out,
+                color_a_out,
+            ]);
+        }
+    }
+}
+
+#[test]
+fn test_feather_rectangle_outline() {
+    let mut positions = vec![
+        [0.0, 0.0],
+        [1.0, 0.0],
+        [1.0, 1.0],
+        [0.0, 0.0],
+        [1.0, 1.0],
+        [0.0, 1.0],
+    ];
+    let mut colors = vec![[1.0; 4]; 6];
+    feather_edges(&mut positions, &mut colors, [0.1, 0.1]);
+
+    // The shared diagonal gets no feather, the four sides get two triangles each.
+    assert_eq!(positions.len(), 6 + 4 * 6);
+    assert_eq!(colors.len(), positions.len());
+    for p in &positions[6..] {
+        assert!(p[0] <= 0.0 || p[0] >= 1.0 || p[1] <= 0.0 || p[1] >= 1.0);
+    }
+    assert_eq!(colors[6 + 2][3], 0.0);
+}
@@ -0,0 +1,217 @@
+//! Packing many small images into a few large textures.
+
+use image::RgbaImage;
+
+use crate::error::Error;
+use crate::texture::check_dimensions;
+use crate::{Format, Texture, TextureSettings, UpdateTexture};
+
+/// The location of an image packed into a `TextureAtlas`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion {
+    /// The index of the atlas page containing the image.
+    pub page: usize,
+    /// The source rectangle `[x, y, width, height]` in pixels,
+    /// for use with `graphics::Image::src_rect`.
+    pub src_rect: [f64; 4],
+}
+
+/// Packs images into one or more large textures.
+///
+/// Drawing images from the same page does not break the `Textured` batch.
+/// Each image is surrounded by copies of its edge pixels,
+/// so filtering does not bleed in colors from neighbouring images.
+pub struct TextureAtlas {
+    page_size: u32,
+    padding: u32,
+    settings: TextureSettings,
+    pages: Vec<Page>,
+}
+
+struct Page {
+    texture: Texture,
+    skyline: Skyline,
+}
+
+impl TextureAtlas {
+    /// Creates an empty atlas with square pages of `page_size` pixels.
+    ///
+    /// The `padding` is the number of edge pixels repeated around each image.
+    /// Returns an error if the pages are larger than the context supports.
+    pub fn new(page_size: u32, padding: u32, settings: &TextureSettings) -> Result<Self, Error> {
+        check_dimensions(page_size, page_size)?;
+        Ok(TextureAtlas {
+            page_size,
+            padding,
+            settings: *settings,
+            pages: vec![],
+        })
+    }
+
+    /// Packs an image, uploading it with `UpdateTexture::update`.
+    ///
+    /// A new page is added when the image does not fit on the existing ones.
+    pub fn insert(&mut self, img: &RgbaImage) -> Result<AtlasRegion, Error> {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return Err(Error::Texture("Can not pack an empty image".into()));
+        }
+        let padded = |len: u32| {
+            self.padding
+                .checked_mul(2)
+                .and_then(|padding| len.checked_add(padding))
+                .filter(|&len| len <= self.page_size)
+        };
+        let size = match (padded(width), padded(height)) {
+            (Some(w), Some(h)) => [w, h],
+            _ => {
+                return Err(Error::Texture(format!(
+                    "Image of {}x{} does not fit into an atlas page of {}x{}",
+                    width, height, self.page_size, self.page_size
+                )))
+            }
+        };
+
+        let found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, page)| page.skyline.insert(size).map(|pos| (i, pos)));
+        let (page, pos) = match found {
+            Some(found) => found,
+            None => {
+                // Allocate without uploading a page of zeros, the images are written next.
+                let texture = Texture::allocate([self.page_size, self.page_size], &self.settings);
+                let mut skyline = Skyline::new(self.page_size);
+                let pos = skyline.insert(size).unwrap();
+                self.pages.push(Page { texture, skyline });
+                (self.pages.len() - 1, pos)
+            }
+        };
+
+        let padded = extrude(img, self.padding);
+        let texture = &mut self.pages[page].texture;
+        UpdateTexture::update(texture, &mut (), Format::Rgba8, &padded, pos, size)?;
+        Ok(AtlasRegion {
+            page,
+            src_rect: [
+                (pos[0] + self.padding) as f64,
+                (pos[1] + self.padding) as f64,
+                width as f64,
+                height as f64,
+            ],
+        })
+    }
+
+    /// Gets the texture of the page containing a region.
+    pub fn texture(&self, region: &AtlasRegion) -> &Texture {
+        &self.pages[region.page].texture
+    }
+
+    /// Gets the textures of all pages.
+    pub fn pages(&self) -> impl Iterator<Item = &Texture> {
+        self.pages.iter().map(|page| &page.texture)
+    }
+
+    /// Gets the number of pages.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+// Copies an image into the middle of a larger one, repeating the edge pixels.
+fn extrude(img: &RgbaImage, padding: u32) -> Vec<u8> {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width + 2 * padding, height + 2 * padding);
+    let mut memory = Vec::with_capacity(w as usize * h as usize * 4);
+    for y in 0..h {
+        let src_y = y.saturating_sub(padding).min(height - 1);
+        for x in 0..w {
+            let src_x = x.saturating_sub(padding).min(width - 1);
+            memory.extend_from_slice(&img.get_pixel(src_x, src_y).0);
+        }
+    }
+    memory
+}
+
+// Tracks the top edge of the packed rectangles as horizontal segments.
+struct Skyline {
+    size: u32,
+    // Segments `[x, y, width]` from left to right.
+    segments: Vec<[u32; 3]>,
+}
+
+impl Skyline {
+    fn new(size: u32) -> Self {
+        Skyline {
+            size,
+            segments: vec![[0, 0, size]],
+        }
+    }
+
+    // Finds the lowest position for a rectangle and reserves it.
+    fn insert(&mut self, size: [u32; 2]) -> Option<[u32; 2]> {
+        let mut best: Option<(usize, [u32; 2])> = None;
+        for i in 0..self.segments.len() {
+            let x = self.segments[i][0];
+            if x + size[0] > self.size {
+                break;
+            }
+            // The rectangle rests on the highest segment below it.
+            let mut y = 0;
+            let mut covered = 0;
+            for segment in &self.segments[i..] {
+                if covered >= size[0] {
+                    break;
+                }
+                y = y.max(segment[1]);
+                covered += segment[2];
+            }
+            if y + size[1] > self.size {
+                continue;
+            }
+            if !matches!(best, Some((_, pos)) if pos[1] <= y) {
+                best = Some((i, [x, y]));
+            }
+        }
+
+        let (i, pos) = best?;
+        self.segments.insert(i, [pos[0], pos[1] + size[1], size[0]]);
+
+        // Shrink or remove the segments now covered by the rectangle.
+        let right = pos[0] + size[0];
+        while i + 1 < self.segments.len() && self.segments[i + 1][0] < right {
+            let next = &mut self.segments[i + 1];
+            let end = next[0] + next[2];
+            if end <= right {
+                self.segments.remove(i + 1);
+            } else {
+                next[2] = end - right;
+                next[0] = right;
+                break;
+            }
+        }
+
+        // Merge neighbours at the same height.
+        let mut j = 0;
+        while j + 1 < self.segments.len() {
+            if self.segments[j][1] == self.segments[j + 1][1] {
+                self.segments[j][2] += self.segments[j + 1][2];
+                self.segments.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+        Some(pos)
+    }
+}
+
+#[test]
+fn test_skyline() {
+    let mut skyline = Skyline::new(10);
+    assert_eq!(skyline.insert([6, 4]), Some([0, 0]));
+    assert_eq!(skyline.insert([4, 2]), Some([6, 0]));
+    assert_eq!(skyline.insert([4, 2]), Some([6, 2]));
+    assert_eq!(skyline.insert([10, 6]), Some([0, 4]));
+    assert_eq!(skyline.insert([1, 1]), None);
+}
@@ -3,15 +3,26 @@
 // External crates.
 use gl::types::{GLint, GLsizei, GLuint};
 use graphics::color::gamma_srgb_to_linear;
+use graphics::math::Matrix2d;
 use graphics::BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
 use graphics::{Context, DrawState, Graphics, Viewport};
 use shader_version::glsl::GLSL;
 use shader_version::{OpenGL, Shaders};
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::mem;
 
 // Local crate.
+use crate::antialias;
+use crate::debug;
 use crate::draw_state;
-use crate::shader_utils::{compile_shader, DynamicAttribute};
+use crate::error::{Error, ShaderStage};
+use crate::garbage::{self, Garbage};
+use crate::gpu_timer::{self, GpuTimer, GpuTiming};
+use crate::gradient::{Gradient, GradientFill};
+use crate::sampler::{self, Sampler};
+use crate::sdf::{self, Sdf, SdfShape};
+use crate::shader_utils::{compile_shader, link_program, DynamicAttribute};
+use crate::video::{VideoTexture, Yuv};
 use crate::Texture;
 
 // The number of chunks to fill up before rendering.
@@ -19,16 +30,22 @@ use crate::Texture;
 // `4` for bytes per f32, and `2 + 4` for position and color.
 const CHUNKS: usize = 100;
 
+// The width of feathered edges in pixels, when anti-aliasing is enabled.
+const FEATHER_WIDTH: f32 = 1.0;
+
 // Whether to use WebGL-specific features; currently used to select appropriate shaders.
-const USE_WEBGL: bool = cfg!(all(target_arch = "wasm32", target_os = "unknown"))
+pub(crate) const USE_WEBGL: bool = cfg!(all(target_arch = "wasm32", target_os = "unknown"))
     || cfg!(target_os = "emscripten")
     || cfg!(feature = "webgl");
 
 /// Describes how to render colored objects.
 pub struct Colored {
+    glsl: GLSL,
     vao: GLuint,
     vertex_shader: GLuint,
     fragment_shader: GLuint,
+    vertex_source: String,
+    fragment_source: String,
     program: GLuint,
     pos: DynamicAttribute,
     color: DynamicAttribute,
@@ -39,12 +56,10 @@ pub struct Colored {
 
 impl Drop for Colored {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &self.vao);
-            gl::DeleteProgram(self.program);
-            gl::DeleteShader(self.vertex_shader);
-            gl::DeleteShader(self.fragment_shader);
-        }
+        garbage::delete(Garbage::VertexArray(self.vao));
+        garbage::delete(Garbage::Program(self.program));
+        garbage::delete(Garbage::Shader(self.vertex_shader));
+        garbage::delete(Garbage::Shader(self.fragment_shader));
     }
 }
 
@@ -87,45 +102,65 @@ impl Colored {
         glsl: GLSL,
         vertex_shaders: &Shaders<GLSL, str>,
         fragment_shaders: &Shaders<GLSL, str>,
-    ) -> Result<Self, String> {
-        let v_shader = vertex_shaders
-            .get(glsl)
-            .ok_or("No compatible vertex shader")?;
+    ) -> Result<Self, Error> {
+        let v_shader = vertex_shaders.get(glsl).ok_or(Error::MissingShader {
+            stage: ShaderStage::Vertex,
+            glsl,
+        })?;
+
+        let f_shader = fragment_shaders.get(glsl).ok_or(Error::MissingShader {
+            stage: ShaderStage::Fragment,
+            glsl,
+        })?;
+
+        Colored::from_sources(glsl, v_shader, f_shader)
+    }
 
-        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)
-            .map_err(|s| format!("Error compiling vertex shader: {}", s))?;
+    /// Recreates the OpenGL objects after the context was lost.
+    ///
+    /// The old objects belong to the lost context and are not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh = Colored::from_sources(self.glsl, &self.vertex_source, &self.fragment_source)?;
+        mem::replace(self, fresh).invalidate();
+        Ok(())
+    }
 
-        let f_shader = fragment_shaders
-            .get(glsl)
-            .ok_or("No compatible fragment shader")?;
+    fn from_sources(glsl: GLSL, v_shader: &str, f_shader: &str) -> Result<Self, Error> {
+        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)?;
 
-        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)
-            .map_err(|s| format!("Error compiling fragment shader: {}", s))?;
+        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)?;
 
         let program;
         unsafe {
-            program = gl::CreateProgram();
-            gl::AttachShader(program, v_shader_compiled);
-            gl::AttachShader(program, f_shader_compiled);
+            program = gl_check!(CreateProgram());
+            gl_check!(AttachShader(program, v_shader_compiled));
+            gl_check!(AttachShader(program, f_shader_compiled));
 
             let c_o_color = CString::new("o_Color").unwrap();
             if !USE_WEBGL {
-                gl::BindFragDataLocation(program, 0, c_o_color.as_ptr());
+                gl_check!(BindFragDataLocation(program, 0, c_o_color.as_ptr()));
             }
             drop(c_o_color);
         }
 
         let mut vao = 0;
         unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::LinkProgram(program);
+            gl_check!(GenVertexArrays(1, &mut vao));
         }
-        let pos = DynamicAttribute::xy(program, "pos", vao).unwrap();
-        let color = DynamicAttribute::rgba(program, "color", vao).unwrap();
+        link_program(program)?;
+        let pos = DynamicAttribute::xy(program, "pos", vao)?;
+        let color = DynamicAttribute::rgba(program, "color", vao)?;
+        debug::label(gl::PROGRAM, program, "Colored");
+        debug::label(gl::VERTEX_ARRAY, vao, "Colored");
+        pos.label("Colored pos");
+        color.label("Colored color");
         Ok(Colored {
+            glsl,
             vao,
             vertex_shader: v_shader_compiled,
             fragment_shader: f_shader_compiled,
+            vertex_source: v_shader.to_string(),
+            fragment_source: f_shader.to_string(),
             program,
             pos,
             color,
@@ -135,16 +170,26 @@ impl Colored {
         })
     }
 
+    // Forgets the OpenGL objects without deleting them.
+    fn invalidate(&mut self) {
+        self.vao = 0;
+        self.program = 0;
+        self.vertex_shader = 0;
+        self.fragment_shader = 0;
+        self.pos.invalidate();
+        self.color.invalidate();
+    }
+
     fn flush(&mut self) {
         unsafe {
-            gl::BindVertexArray(self.vao);
+            gl_check!(BindVertexArray(self.vao));
             // Render triangles whether they are facing
             // clockwise or counter clockwise.
-            gl::Disable(gl::CULL_FACE);
+            gl_check!(Disable(gl::CULL_FACE));
             self.color.set(&self.color_buffer[..self.offset]);
             self.pos.set(&self.pos_buffer[..self.offset]);
-            gl::DrawArrays(gl::TRIANGLES, 0, self.offset as i32);
-            gl::BindVertexArray(0);
+            gl_check!(DrawArrays(gl::TRIANGLES, 0, self.offset as i32));
+            gl_check!(BindVertexArray(0));
         }
 
         self.offset = 0;
@@ -155,6 +200,8 @@ impl Colored {
 pub struct Textured {
     vertex_shader: GLuint,
     fragment_shader: GLuint,
+    vertex_source: String,
+    fragment_source: String,
     program: GLuint,
     vao: GLuint,
     color: GLint,
@@ -164,17 +211,19 @@ pub struct Textured {
     uv_buffer: Vec<[f32; 2]>,
     offset: usize,
     last_texture_id: GLuint,
+    last_sampler: GLuint,
     last_color: [f32; 4],
 }
 
 impl Drop for Textured {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &self.vao);
-            gl::DeleteProgram(self.program);
-            gl::DeleteShader(self.vertex_shader);
-            gl::DeleteShader(self.fragment_shader);
+        if self.offset > 0 {
+            garbage::unpin_texture(self.last_texture_id);
         }
+        garbage::delete(Garbage::VertexArray(self.vao));
+        garbage::delete(Garbage::Program(self.program));
+        garbage::delete(Garbage::Shader(self.vertex_shader));
+        garbage::delete(Garbage::Shader(self.fragment_shader));
     }
 }
 
@@ -217,51 +266,70 @@ impl Textured {
         glsl: GLSL,
         vertex_shaders: &Shaders<GLSL, str>,
         fragment_shaders: &Shaders<GLSL, str>,
-    ) -> Result<Self, String> {
-        let v_shader = vertex_shaders
-            .get(glsl)
-            .ok_or("No compatible vertex shader")?;
+    ) -> Result<Self, Error> {
+        let v_shader = vertex_shaders.get(glsl).ok_or(Error::MissingShader {
+            stage: ShaderStage::Vertex,
+            glsl,
+        })?;
+
+        let f_shader = fragment_shaders.get(glsl).ok_or(Error::MissingShader {
+            stage: ShaderStage::Fragment,
+            glsl,
+        })?;
+
+        Textured::from_sources(v_shader, f_shader)
+    }
 
-        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)
-            .map_err(|s| format!("Error compiling vertex shader: {}", s))?;
+    /// Recreates the OpenGL objects after the context was lost.
+    ///
+    /// The old objects belong to the lost context and are not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh = Textured::from_sources(&self.vertex_source, &self.fragment_source)?;
+        mem::replace(self, fresh).invalidate();
+        Ok(())
+    }
 
-        let f_shader = fragment_shaders
-            .get(glsl)
-            .ok_or("No compatible fragment shader")?;
+    fn from_sources(v_shader: &str, f_shader: &str) -> Result<Self, Error> {
+        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)?;
 
-        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)
-            .map_err(|s| format!("Error compiling fragment shader: {}", s))?;
+        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)?;
 
         let program;
         unsafe {
-            program = gl::CreateProgram();
-            gl::AttachShader(program, v_shader_compiled);
-            gl::AttachShader(program, f_shader_compiled);
+            program = gl_check!(CreateProgram());
+            gl_check!(AttachShader(program, v_shader_compiled));
+            gl_check!(AttachShader(program, f_shader_compiled));
 
             let c_o_color = CString::new("o_Color").unwrap();
             if !USE_WEBGL {
-                gl::BindFragDataLocation(program, 0, c_o_color.as_ptr());
+                gl_check!(BindFragDataLocation(program, 0, c_o_color.as_ptr()));
             }
             drop(c_o_color);
         }
 
         let mut vao = 0;
         unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::LinkProgram(program);
+            gl_check!(GenVertexArrays(1, &mut vao));
         }
-        let pos = DynamicAttribute::xy(program, "pos", vao).unwrap();
+        link_program(program)?;
+        let pos = DynamicAttribute::xy(program, "pos", vao)?;
         let c_color = CString::new("color").unwrap();
-        let color = unsafe { gl::GetUniformLocation(program, c_color.as_ptr()) };
+        let color = unsafe { gl_check!(GetUniformLocation(program, c_color.as_ptr())) };
         drop(c_color);
         if color == -1 {
-            panic!("Could not find uniform `color`");
+            return Err(Error::MissingUniform("color".into()));
         }
-        let uv = DynamicAttribute::uv(program, "uv", vao).unwrap();
+        let uv = DynamicAttribute::uv(program, "uv", vao)?;
+        debug::label(gl::PROGRAM, program, "Textured");
+        debug::label(gl::VERTEX_ARRAY, vao, "Textured");
+        pos.label("Textured pos");
+        uv.label("Textured uv");
         Ok(Textured {
             vao,
             vertex_shader: v_shader_compiled,
             fragment_shader: f_shader_compiled,
+            vertex_source: v_shader.to_string(),
+            fragment_source: f_shader.to_string(),
             program,
             pos,
             color,
@@ -270,26 +338,48 @@ impl Textured {
             uv_buffer: vec![[0.0; 2]; CHUNKS * BUFFER_SIZE],
             offset: 0,
             last_texture_id: 0,
+            last_sampler: 0,
             last_color: [0.0; 4],
         })
     }
 
+    // Forgets the OpenGL objects without deleting them.
+    fn invalidate(&mut self) {
+        self.vao = 0;
+        self.program = 0;
+        self.vertex_shader = 0;
+        self.fragment_shader = 0;
+        self.pos.invalidate();
+        self.uv.invalidate();
+    }
+
     fn flush(&mut self) {
         let texture_id = self.last_texture_id;
         let color = self.last_color;
         unsafe {
-            gl::BindVertexArray(self.vao);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
-            gl::Uniform4f(self.color, color[0], color[1], color[2], color[3]);
+            gl_check!(BindVertexArray(self.vao));
+            gl_check!(BindTexture(gl::TEXTURE_2D, texture_id));
+            if self.last_sampler != 0 {
+                gl_check!(BindSampler(0, self.last_sampler));
+            }
+            gl_check!(Uniform4f(
+                self.color, color[0], color[1], color[2], color[3]
+            ));
             // Render triangles whether they are facing
             // clockwise or counter clockwise.
-            gl::Disable(gl::CULL_FACE);
+            gl_check!(Disable(gl::CULL_FACE));
             self.pos.set(&self.pos_buffer[..self.offset]);
             self.uv.set(&self.uv_buffer[..self.offset]);
-            gl::DrawArrays(gl::TRIANGLES, 0, self.offset as i32);
-            gl::BindVertexArray(0);
+            gl_check!(DrawArrays(gl::TRIANGLES, 0, self.offset as i32));
+            gl_check!(BindVertexArray(0));
+            if self.last_sampler != 0 {
+                gl_check!(BindSampler(0, 0));
+            }
         }
 
+        if self.offset > 0 {
+            garbage::unpin_texture(texture_id);
+        }
         self.offset = 0;
     }
 }
@@ -298,6 +388,8 @@ impl Textured {
 pub struct TexturedColor {
     vertex_shader: GLuint,
     fragment_shader: GLuint,
+    vertex_source: String,
+    fragment_source: String,
     program: GLuint,
     vao: GLuint,
     pos: DynamicAttribute,
@@ -308,16 +400,18 @@ pub struct TexturedColor {
     color_buffer: Vec<[f32; 4]>,
     offset: usize,
     last_texture_id: GLuint,
+    last_sampler: GLuint,
 }
 
 impl Drop for TexturedColor {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &self.vao);
-            gl::DeleteProgram(self.program);
-            gl::DeleteShader(self.vertex_shader);
-            gl::DeleteShader(self.fragment_shader);
+        if self.offset > 0 {
+            garbage::unpin_texture(self.last_texture_id);
         }
+        garbage::delete(Garbage::VertexArray(self.vao));
+        garbage::delete(Garbage::Program(self.program));
+        garbage::delete(Garbage::Shader(self.vertex_shader));
+        garbage::delete(Garbage::Shader(self.fragment_shader));
     }
 }
 
@@ -363,46 +457,66 @@ impl TexturedColor {
         glsl: GLSL,
         vertex_shaders: &Shaders<GLSL, str>,
         fragment_shaders: &Shaders<GLSL, str>,
-    ) -> Result<Self, String> {
-        let v_shader = vertex_shaders
-            .get(glsl)
-            .ok_or("No compatible vertex shader")?;
+    ) -> Result<Self, Error> {
+        let v_shader = vertex_shaders.get(glsl).ok_or(Error::MissingShader {
+            stage: ShaderStage::Vertex,
+            glsl,
+        })?;
+
+        let f_shader = fragment_shaders.get(glsl).ok_or(Error::MissingShader {
+            stage: ShaderStage::Fragment,
+            glsl,
+        })?;
+
+        TexturedColor::from_sources(v_shader, f_shader)
+    }
 
-        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)
-            .map_err(|s| format!("Error compiling vertex shader: {}", s))?;
+    /// Recreates the OpenGL objects after the context was lost.
+    ///
+    /// The old objects belong to the lost context and are not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh = TexturedColor::from_sources(&self.vertex_source, &self.fragment_source)?;
+        mem::replace(self, fresh).invalidate();
+        Ok(())
+    }
 
-        let f_shader = fragment_shaders
-            .get(glsl)
-            .ok_or("No compatible fragment shader")?;
+    fn from_sources(v_shader: &str, f_shader: &str) -> Result<Self, Error> {
+        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)?;
 
-        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)
-            .map_err(|s| format!("Error compiling fragment shader: {}", s))?;
+        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)?;
 
         let program;
         unsafe {
-            program = gl::CreateProgram();
-            gl::AttachShader(program, v_shader_compiled);
-            gl::AttachShader(program, f_shader_compiled);
+            program = gl_check!(CreateProgram());
+            gl_check!(AttachShader(program, v_shader_compiled));
+            gl_check!(AttachShader(program, f_shader_compiled));
 
             let c_o_color = CString::new("o_Color").unwrap();
             if !USE_WEBGL {
-                gl::BindFragDataLocation(program, 0, c_o_color.as_ptr());
+                gl_check!(BindFragDataLocation(program, 0, c_o_color.as_ptr()));
             }
             drop(c_o_color);
         }
 
         let mut vao = 0;
         unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-            gl::LinkProgram(program);
+            gl_check!(GenVertexArrays(1, &mut vao));
         }
-        let pos = DynamicAttribute::xy(program, "pos", vao).unwrap();
-        let color = DynamicAttribute::rgba(program, "color", vao).unwrap();
-        let uv = DynamicAttribute::uv(program, "uv", vao).unwrap();
+        link_program(program)?;
+        let pos = DynamicAttribute::xy(program, "pos", vao)?;
+        let color = DynamicAttribute::rgba(program, "color", vao)?;
+        let uv = DynamicAttribute::uv(program, "uv", vao)?;
+        debug::label(gl::PROGRAM, program, "TexturedColor");
+        debug::label(gl::VERTEX_ARRAY, vao, "TexturedColor");
+        pos.label("TexturedColor pos");
+        color.label("TexturedColor color");
+        uv.label("TexturedColor uv");
         Ok(TexturedColor {
             vao,
             vertex_shader: v_shader_compiled,
             fragment_shader: f_shader_compiled,
+            vertex_source: v_shader.to_string(),
+            fragment_source: f_shader.to_string(),
             program,
             pos,
             color,
@@ -412,28 +526,65 @@ impl TexturedColor {
             color_buffer: vec![[0.0; 4]; CHUNKS * BUFFER_SIZE],
             offset: 0,
             last_texture_id: 0,
+            last_sampler: 0,
         })
     }
 
+    // Forgets the OpenGL objects without deleting them.
+    fn invalidate(&mut self) {
+        self.vao = 0;
+        self.program = 0;
+        self.vertex_shader = 0;
+        self.fragment_shader = 0;
+        self.pos.invalidate();
+        self.uv.invalidate();
+        self.color.invalidate();
+    }
+
     fn flush(&mut self) {
         let texture_id = self.last_texture_id;
         unsafe {
-            gl::BindVertexArray(self.vao);
-            gl::BindTexture(gl::TEXTURE_2D, texture_id);
+            gl_check!(BindVertexArray(self.vao));
+            gl_check!(BindTexture(gl::TEXTURE_2D, texture_id));
+            if self.last_sampler != 0 {
+                gl_check!(BindSampler(0, self.last_sampler));
+            }
             // Render triangles whether they are facing
             // clockwise or counter clockwise.
-            gl::Disable(gl::CULL_FACE);
+            gl_check!(Disable(gl::CULL_FACE));
             self.pos.set(&self.pos_buffer[..self.offset]);
             self.uv.set(&self.uv_buffer[..self.offset]);
             self.color.set(&self.color_buffer[..self.offset]);
-            gl::DrawArrays(gl::TRIANGLES, 0, self.offset as i32);
-            gl::BindVertexArray(0);
+            gl_check!(DrawArrays(gl::TRIANGLES, 0, self.offset as i32));
+            gl_check!(BindVertexArray(0));
+            if self.last_sampler != 0 {
+                gl_check!(BindSampler(0, 0));
+            }
         }
 
+        if self.offset > 0 {
+            garbage::unpin_texture(texture_id);
+        }
         self.offset = 0;
     }
 }
 
+// Checks whether the context supports one of the named extensions.
+pub(crate) fn has_extension(names: &[&str]) -> bool {
+    let mut count: GLint = 0;
+    unsafe {
+        gl_check!(GetIntegerv(gl::NUM_EXTENSIONS, &mut count));
+    }
+    (0..count).any(|i| {
+        let name = unsafe { gl_check!(GetStringi(gl::EXTENSIONS, i as u32)) };
+        if name.is_null() {
+            return false;
+        }
+        let name = unsafe { CStr::from_ptr(name as *const _) };
+        names.iter().any(|n| n.as_bytes() == name.to_bytes())
+    })
+}
+
 // Newlines and indents for cleaner panic message.
 const GL_FUNC_NOT_LOADED: &str = "
     OpenGL function pointers must be loaded before creating the `Gl` backend!
@@ -441,6 +592,14 @@ const GL_FUNC_NOT_LOADED: &str = "
     https://github.com/PistonDevelopers/opengl_graphics/issues/103
 ";
 
+// The pipelines created on first use, batched separately from the built-in shaders.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pipeline {
+    Gradient,
+    Sdf,
+    Yuv,
+}
+
 /// Contains OpenGL data.
 pub struct GlGraphics {
     colored: Colored,
@@ -452,6 +611,23 @@ pub struct GlGraphics {
     current_draw_state: Option<DrawState>,
     // Keeps track of the current viewport
     current_viewport: Option<Viewport>,
+    // Whether a context loss was detected and not yet restored.
+    context_lost: bool,
+    // Measures frames on the GPU, when enabled.
+    gpu_timer: Option<GpuTimer>,
+    // Whether colored shapes get feathered edges.
+    antialias: bool,
+    // Collects the triangles of a colored shape to find its outer edges.
+    antialias_positions: Vec<[f32; 2]>,
+    antialias_colors: Vec<[f32; 4]>,
+    // Renders gradient fills, created on first use.
+    gradient: Option<Gradient>,
+    // Renders shapes from signed distance fields, created on first use.
+    sdf: Option<Sdf>,
+    // Renders video frames converted from YUV, created on first use.
+    yuv: Option<Yuv>,
+    // The sampler object used for textured triangles, `0` for the texture settings.
+    sampler: GLuint,
 }
 
 impl GlGraphics {
@@ -472,6 +648,15 @@ impl GlGraphics {
             current_program: None,
             current_draw_state: None,
             current_viewport: None,
+            context_lost: false,
+            gpu_timer: None,
+            antialias: false,
+            antialias_positions: vec![],
+            antialias_colors: vec![],
+            gradient: None,
+            sdf: None,
+            yuv: None,
+            sampler: 0,
         }
     }
 
@@ -496,13 +681,79 @@ impl GlGraphics {
             current_program: None,
             current_draw_state: None,
             current_viewport: None,
+            context_lost: false,
+            gpu_timer: None,
+            antialias: false,
+            antialias_positions: vec![],
+            antialias_colors: vec![],
+            gradient: None,
+            sdf: None,
+            yuv: None,
+            sampler: 0,
         }
     }
 
+    /// Checks whether the OpenGL context was lost.
+    ///
+    /// Returns `Error::ContextLost` once a loss is detected,
+    /// until `restore` is called.
+    /// With the `glow` feature, a loss is detected in checked mode
+    /// or after `set_context_lost` was called.
+    pub fn check_context(&mut self) -> Result<(), Error> {
+        if !self.context_lost
+            && gl::GetGraphicsResetStatus::is_loaded()
+            && unsafe { gl::GetGraphicsResetStatus() } != gl::NO_ERROR
+        {
+            self.context_lost = true;
+        }
+        if self.context_lost {
+            Err(Error::ContextLost)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if a context loss was detected by `check_context`.
+    pub fn is_context_lost(&self) -> bool {
+        self.context_lost
+    }
+
+    /// Recreates the built-in pipelines after the context was restored.
+    ///
+    /// Textures, gradient fills and samplers are not owned by the back-end
+    /// and must be restored separately, see `Texture::restore`.
+    /// The sampler in use is reset, set it again with `use_sampler`.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        garbage::discard();
+        sampler::reset_max_anisotropy();
+        self.colored.restore()?;
+        self.textured.restore()?;
+        self.textured_color.restore()?;
+        if let Some(ref mut gradient) = self.gradient {
+            gradient.restore()?;
+        }
+        if let Some(ref mut sdf) = self.sdf {
+            sdf.restore()?;
+        }
+        if let Some(ref mut yuv) = self.yuv {
+            yuv.restore()?;
+        }
+        if let Some(ref mut gpu_timer) = self.gpu_timer {
+            gpu_timer.invalidate();
+        }
+        self.current_program = None;
+        self.current_draw_state = None;
+        self.sampler = 0;
+        self.context_lost = false;
+        #[cfg(feature = "glow")]
+        gl::set_context_lost(false);
+        Ok(())
+    }
+
     /// Sets viewport with normalized coordinates and center as origin.
     fn viewport(&mut self, x: i32, y: i32, w: i32, h: i32) {
         unsafe {
-            gl::Viewport(x as GLint, y as GLint, w as GLsizei, h as GLsizei);
+            gl_check!(Viewport(x as GLint, y as GLint, w as GLsizei, h as GLsizei));
         }
     }
 
@@ -523,7 +774,7 @@ impl GlGraphics {
         }
 
         unsafe {
-            gl::UseProgram(program);
+            gl_check!(UseProgram(program));
         }
         self.current_program = Some(program);
     }
@@ -559,19 +810,153 @@ impl GlGraphics {
 
     /// Setup that should be called at the start of a frame's draw call.
     pub fn draw_begin(&mut self, viewport: Viewport) -> Context {
+        // Triangles batched outside of a frame may still use dropped textures.
+        self.flush();
+        garbage::collect();
         let rect = viewport.rect;
         let (x, y, w, h) = (rect[0], rect[1], rect[2], rect[3]);
         self.viewport(x, y, w, h);
         self.current_viewport = Some(viewport);
         self.clear_program();
         unsafe {
-            gl::Enable(gl::FRAMEBUFFER_SRGB);
+            gl_check!(Enable(gl::FRAMEBUFFER_SRGB));
+        }
+        if let Some(ref mut gpu_timer) = self.gpu_timer {
+            gpu_timer.begin_frame();
         }
         Context::new_viewport(viewport)
     }
 
     /// Finalize the frame's draw calls.
     pub fn draw_end(&mut self) {
+        self.flush();
+        if let Some(ref mut gpu_timer) = self.gpu_timer {
+            gpu_timer.end_frame();
+        }
+        garbage::collect();
+    }
+
+    /// Deletes the OpenGL objects of dropped textures, shaders and buffers.
+    ///
+    /// Dropping only queues the objects, which is safe on any thread.
+    /// The queue is emptied by `draw_begin` and `draw_end`,
+    /// call this to free memory outside of drawing, for example after unloading a level.
+    /// Headless users that never draw must call this regularly,
+    /// otherwise the queue keeps growing.
+    /// Queued objects are forgotten by `restore`, since they belong to the lost context.
+    ///
+    /// The queue is shared by the whole process, so with several `GlGraphics`
+    /// their contexts must share objects, or only one of them may be used.
+    /// Textures still batched by any `GlGraphics` stay queued until that batch is flushed.
+    pub fn collect_garbage(&mut self) {
+        self.flush();
+        garbage::collect();
+    }
+
+    /// Enables or disables measuring frames with GPU timer queries.
+    ///
+    /// Results are available a few frames later through `gpu_timing`.
+    /// Returns `false` if timer queries are not supported by the context,
+    /// which leaves the timer disabled.
+    pub fn set_gpu_timer(&mut self, enabled: bool) -> bool {
+        if enabled && !gpu_timer::is_supported() {
+            self.gpu_timer = None;
+            return false;
+        }
+        if enabled != self.gpu_timer.is_some() {
+            self.gpu_timer = if enabled { Some(GpuTimer::new()) } else { None };
+        }
+        true
+    }
+
+    /// Returns the GPU timing of the latest frame with available results.
+    pub fn gpu_timing(&self) -> Option<&GpuTiming> {
+        self.gpu_timer
+            .as_ref()
+            .and_then(|gpu_timer| gpu_timer.latest())
+    }
+
+    /// Starts measuring a labelled section of the frame on the GPU.
+    ///
+    /// Sections can not be nested, starting a new section ends the current one.
+    pub fn begin_gpu_section(&mut self, label: &str) {
+        if self.gpu_timer.is_some() {
+            self.flush();
+        }
+        if let Some(ref mut gpu_timer) = self.gpu_timer {
+            gpu_timer.begin_section(label);
+        }
+    }
+
+    /// Ends measuring the current section of the frame on the GPU.
+    pub fn end_gpu_section(&mut self) {
+        if self.gpu_timer.is_some() {
+            self.flush();
+        }
+        if let Some(ref mut gpu_timer) = self.gpu_timer {
+            gpu_timer.end_section();
+        }
+    }
+
+    /// Enables or disables anti-aliasing of colored shapes.
+    ///
+    /// The outer edges of each shape get a feather fading to transparent,
+    /// which gives smooth edges without multisampling.
+    pub fn set_antialias(&mut self, enabled: bool) {
+        self.antialias = enabled;
+    }
+
+    /// Returns `true` if colored shapes are anti-aliased.
+    pub fn get_antialias(&self) -> bool {
+        self.antialias
+    }
+
+    // Adds feathered edges to the collected shape and renders it.
+    fn push_antialiased(&mut self) {
+        let mut positions = mem::take(&mut self.antialias_positions);
+        let mut colors = mem::take(&mut self.antialias_colors);
+        if let Some(viewport) = self.current_viewport {
+            let width = [
+                2.0 * FEATHER_WIDTH / viewport.rect[2] as f32,
+                2.0 * FEATHER_WIDTH / viewport.rect[3] as f32,
+            ];
+            antialias::feather_edges(&mut positions, &mut colors, width);
+        }
+
+        // Split at triangle borders when the buffer is full.
+        let capacity = BUFFER_SIZE * CHUNKS - BUFFER_SIZE * CHUNKS % 3;
+        for (vertices, colors) in positions.chunks(capacity).zip(colors.chunks(capacity)) {
+            let items = vertices.len();
+
+            // Render if there is not enough room.
+            if self.colored.offset + items > BUFFER_SIZE * CHUNKS {
+                let program = self.colored.program;
+                self.use_program(program);
+                self.colored.flush();
+            }
+
+            let shader = &mut self.colored;
+            shader.color_buffer[shader.offset..shader.offset + items].copy_from_slice(colors);
+            shader.pos_buffer[shader.offset..shader.offset + items].copy_from_slice(vertices);
+            shader.offset += items;
+        }
+
+        positions.clear();
+        colors.clear();
+        self.antialias_positions = positions;
+        self.antialias_colors = colors;
+    }
+
+    // Renders all batched triangles.
+    fn flush(&mut self) {
+        self.flush_builtin();
+        self.flush_gradient();
+        self.flush_sdf();
+        self.flush_yuv();
+    }
+
+    // Renders the batched triangles of the built-in shaders.
+    fn flush_builtin(&mut self) {
         if self.colored.offset > 0 {
             let program = self.colored.program;
             self.use_program(program);
@@ -589,6 +974,195 @@ impl GlGraphics {
         }
     }
 
+    // Renders the batched triangles of an optional pipeline.
+    fn flush_pipeline(&mut self, pipeline: Pipeline) {
+        match pipeline {
+            Pipeline::Gradient => self.flush_gradient(),
+            Pipeline::Sdf => self.flush_sdf(),
+            Pipeline::Yuv => self.flush_yuv(),
+        }
+    }
+
+    // Renders the batches of all other pipelines before batching into `pipeline`,
+    // and flushes `pipeline` when the draw state or its `changed` state differs.
+    fn prepare_pipeline(&mut self, pipeline: Pipeline, draw_state: &DrawState, changed: bool) {
+        self.flush_builtin();
+        for &other in &[Pipeline::Gradient, Pipeline::Sdf, Pipeline::Yuv] {
+            if other != pipeline {
+                self.flush_pipeline(other);
+            }
+        }
+
+        // Flush when draw state changes.
+        if self.current_draw_state.is_none()
+            || self.current_draw_state.as_ref().unwrap() != draw_state
+            || changed
+        {
+            if self.current_draw_state.is_none() {
+                self.use_draw_state(&Default::default());
+            }
+            self.flush_pipeline(pipeline);
+            self.use_draw_state(draw_state);
+        }
+    }
+
+    // Renders the batched gradient triangles.
+    fn flush_gradient(&mut self) {
+        let program = match self.gradient {
+            Some(ref gradient) if gradient.offset > 0 => gradient.program,
+            _ => return,
+        };
+        self.use_program(program);
+        self.gradient.as_mut().unwrap().flush();
+    }
+
+    // Renders the batched signed distance field shapes.
+    fn flush_sdf(&mut self) {
+        let program = match self.sdf {
+            Some(ref sdf) if sdf.offset > 0 => sdf.program,
+            _ => return,
+        };
+        self.use_program(program);
+        self.sdf.as_mut().unwrap().flush();
+    }
+
+    // Renders the batched video frames.
+    fn flush_yuv(&mut self) {
+        let program = match self.yuv {
+            Some(ref yuv) if yuv.offset > 0 => yuv.program,
+            _ => return,
+        };
+        self.use_program(program);
+        self.yuv.as_mut().unwrap().flush();
+    }
+
+    /// Renders triangles filled with a gradient.
+    ///
+    /// The `transform` maps gradient coordinates to normalized device coordinates,
+    /// usually the transform of the context used to compute the vertices.
+    pub fn tri_list_gradient<F>(
+        &mut self,
+        draw_state: &DrawState,
+        fill: &GradientFill,
+        transform: Matrix2d,
+        mut f: F,
+    ) where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]])),
+    {
+        if self.gradient.is_none() {
+            self.gradient = Some(Gradient::new(self.colored.glsl));
+        }
+        let state = fill.state(transform);
+        let changed = self.gradient.as_ref().unwrap().last_state != Some(state);
+        self.prepare_pipeline(Pipeline::Gradient, draw_state, changed);
+
+        self.gradient.as_mut().unwrap().last_state = Some(state);
+        f(&mut |vertices: &[[f32; 2]]| {
+            let items = vertices.len();
+
+            // Render if there is not enough room.
+            let shader = self.gradient.as_ref().unwrap();
+            if shader.offset + items > shader.pos_buffer.len() {
+                self.flush_gradient();
+            }
+
+            let shader = self.gradient.as_mut().unwrap();
+            shader.pos_buffer[shader.offset..shader.offset + items].copy_from_slice(vertices);
+            shader.offset += items;
+        });
+    }
+
+    /// Renders a shape with edges computed per pixel from its signed distance.
+    ///
+    /// Each shape is a single quad, so it stays smooth at any scale,
+    /// and consecutive shapes with the same draw state are batched.
+    pub fn sdf_shape(
+        &mut self,
+        draw_state: &DrawState,
+        shape: &SdfShape,
+        color: &[f32; 4],
+        transform: Matrix2d,
+    ) {
+        let color = gamma_srgb_to_linear(*color);
+
+        if self.sdf.is_none() {
+            self.sdf = Some(Sdf::new(self.colored.glsl));
+        }
+        self.prepare_pipeline(Pipeline::Sdf, draw_state, false);
+
+        // Compute the size of a pixel in the coordinates of the shape.
+        let pixel = match self.current_viewport {
+            Some(viewport) => {
+                let t = transform;
+                let det = (t[0][0] * t[1][1] - t[0][1] * t[1][0]).abs();
+                let (w, h) = (viewport.rect[2] as f64, viewport.rect[3] as f64);
+                1.0 / (0.25 * det * w * h).sqrt()
+            }
+            None => 0.0,
+        };
+        let quad = sdf::quad(shape, transform, pixel);
+
+        // Render if there is not enough room.
+        let shader = self.sdf.as_ref().unwrap();
+        if shader.offset + 6 > shader.pos_buffer.len() {
+            self.flush_sdf();
+        }
+
+        let shader = self.sdf.as_mut().unwrap();
+        let range = shader.offset..shader.offset + 6;
+        shader.pos_buffer[range.clone()].copy_from_slice(&quad.pos);
+        shader.local_buffer[range.clone()].copy_from_slice(&quad.local);
+        for i in range {
+            shader.color_buffer[i] = color;
+            shader.radii_buffer[i] = quad.radii;
+            shader.params_buffer[i] = quad.params;
+        }
+        shader.offset += 6;
+    }
+
+    /// Renders textured triangles from a video frame, converting YUV to RGB.
+    ///
+    /// Consecutive calls with the same frame and draw state are batched.
+    pub fn tri_list_yuv<F>(&mut self, draw_state: &DrawState, video: &VideoTexture, mut f: F)
+    where
+        F: FnMut(&mut dyn FnMut(&[[f32; 2]], &[[f32; 2]])),
+    {
+        if self.yuv.is_none() {
+            self.yuv = Some(Yuv::new(self.colored.glsl));
+        }
+        let state = video.state();
+        let changed = self.yuv.as_ref().unwrap().last_state != Some(state);
+        self.prepare_pipeline(Pipeline::Yuv, draw_state, changed);
+
+        self.yuv.as_mut().unwrap().last_state = Some(state);
+        f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
+            assert_eq!(vertices.len(), texture_coords.len());
+            let items = vertices.len();
+
+            // Render if there is not enough room.
+            let shader = self.yuv.as_ref().unwrap();
+            if shader.offset + items > shader.pos_buffer.len() {
+                self.flush_yuv();
+            }
+
+            let shader = self.yuv.as_mut().unwrap();
+            let range = shader.offset..shader.offset + items;
+            shader.pos_buffer[range.clone()].copy_from_slice(vertices);
+            shader.uv_buffer[range].copy_from_slice(texture_coords);
+            shader.offset += items;
+        });
+    }
+
+    /// Sets the sampler used by the following textured draw calls.
+    ///
+    /// The sampler overrides the filtering and wrapping of the textures,
+    /// so the same texture can be drawn with different settings.
+    /// Pass `None` to use the settings of each texture again.
+    /// The sampler must outlive the draw calls using it.
+    pub fn use_sampler(&mut self, sampler: Option<&Sampler>) {
+        self.sampler = sampler.map(|s| s.get_id()).unwrap_or(0);
+    }
+
     /// Convenience for wrapping draw calls with the begin and end methods.
     ///
     /// This is preferred over using the draw_begin & draw_end methods
@@ -616,15 +1190,15 @@ impl Graphics for GlGraphics {
         let color = gamma_srgb_to_linear(color);
         unsafe {
             let (r, g, b, a) = (color[0], color[1], color[2], color[3]);
-            gl::ClearColor(r, g, b, a);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl_check!(ClearColor(r, g, b, a));
+            gl_check!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
         }
     }
 
     fn clear_stencil(&mut self, value: u8) {
         unsafe {
-            gl::ClearStencil(value as i32);
-            gl::Clear(gl::STENCIL_BUFFER_BIT);
+            gl_check!(ClearStencil(value as i32));
+            gl_check!(Clear(gl::STENCIL_BUFFER_BIT));
         }
     }
 
@@ -644,6 +1218,9 @@ impl Graphics for GlGraphics {
             self.use_program(program);
             self.textured_color.flush();
         }
+        self.flush_gradient();
+        self.flush_sdf();
+        self.flush_yuv();
 
         // Flush when draw state changes.
         if self.current_draw_state.is_none()
@@ -660,6 +1237,16 @@ impl Graphics for GlGraphics {
             self.use_draw_state(draw_state);
         }
 
+        if self.antialias {
+            let (positions, colors) = (&mut self.antialias_positions, &mut self.antialias_colors);
+            f(&mut |vertices: &[[f32; 2]]| {
+                positions.extend_from_slice(vertices);
+                colors.extend(vertices.iter().map(|_| color));
+            });
+            self.push_antialiased();
+            return;
+        }
+
         f(&mut |vertices: &[[f32; 2]]| {
             let items = vertices.len();
 
@@ -693,6 +1280,9 @@ impl Graphics for GlGraphics {
             self.use_program(program);
             self.textured_color.flush();
         }
+        self.flush_gradient();
+        self.flush_sdf();
+        self.flush_yuv();
 
         // Flush when draw state changes.
         if self.current_draw_state.is_none()
@@ -709,6 +1299,16 @@ impl Graphics for GlGraphics {
             self.use_draw_state(draw_state);
         }
 
+        if self.antialias {
+            let (positions, linear) = (&mut self.antialias_positions, &mut self.antialias_colors);
+            f(&mut |vertices: &[[f32; 2]], colors: &[[f32; 4]]| {
+                positions.extend_from_slice(vertices);
+                linear.extend(colors.iter().map(|color| gamma_srgb_to_linear(*color)));
+            });
+            self.push_antialiased();
+            return;
+        }
+
         f(&mut |vertices: &[[f32; 2]], colors: &[[f32; 4]]| {
             let items = vertices.len();
 
@@ -749,11 +1349,15 @@ impl Graphics for GlGraphics {
             self.use_program(program);
             self.textured_color.flush();
         }
+        self.flush_gradient();
+        self.flush_sdf();
+        self.flush_yuv();
 
         // Flush when draw state changes.
         if self.current_draw_state.is_none()
             || self.current_draw_state.as_ref().unwrap() != draw_state
             || self.textured.last_texture_id != texture.get_id()
+            || self.textured.last_sampler != self.sampler
             || self.textured.last_color != color
         {
             let program = self.textured.program;
@@ -768,6 +1372,7 @@ impl Graphics for GlGraphics {
         }
 
         self.textured.last_texture_id = texture.get_id();
+        self.textured.last_sampler = self.sampler;
         self.textured.last_color = color;
         f(&mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]]| {
             let items = vertices.len();
@@ -780,6 +1385,10 @@ impl Graphics for GlGraphics {
             }
 
             let shader = &mut self.textured;
+            if shader.offset == 0 && items > 0 {
+                // Keep the texture alive until the batch is flushed.
+                garbage::pin_texture(shader.last_texture_id);
+            }
             shader.pos_buffer[shader.offset..shader.offset + items].copy_from_slice(vertices);
             shader.uv_buffer[shader.offset..shader.offset + items].copy_from_slice(texture_coords);
             shader.offset += items;
@@ -800,11 +1409,15 @@ impl Graphics for GlGraphics {
             self.use_program(program);
             self.textured.flush();
         }
+        self.flush_gradient();
+        self.flush_sdf();
+        self.flush_yuv();
 
         // Flush when draw state changes.
         if self.current_draw_state.is_none()
             || self.current_draw_state.as_ref().unwrap() != draw_state
             || self.textured_color.last_texture_id != texture.get_id()
+            || self.textured_color.last_sampler != self.sampler
         {
             let program = self.textured_color.program;
             if self.current_draw_state.is_none() {
@@ -818,6 +1431,7 @@ impl Graphics for GlGraphics {
         }
 
         self.textured_color.last_texture_id = texture.get_id();
+        self.textured_color.last_sampler = self.sampler;
         f(
             &mut |vertices: &[[f32; 2]], texture_coords: &[[f32; 2]], colors: &[[f32; 4]]| {
                 let items = vertices.len();
@@ -830,6 +1444,10 @@ impl Graphics for GlGraphics {
                 }
 
                 let shader = &mut self.textured_color;
+                if shader.offset == 0 && items > 0 {
+                    // Keep the texture alive until the batch is flushed.
+                    garbage::pin_texture(shader.last_texture_id);
+                }
                 for (i, color) in colors.iter().enumerate() {
                     shader.color_buffer[shader.offset + i] = gamma_srgb_to_linear(*color);
                 }
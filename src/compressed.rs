@@ -0,0 +1,456 @@
+//! Block compressed textures loaded from KTX2 and DDS containers.
+
+use gl::types::{GLenum, GLint};
+use std::convert::TryFrom;
+
+use crate::back_end::has_extension;
+use crate::error::Error;
+use crate::texture::check_levels;
+
+// Compressed formats missing from the core profile bindings.
+const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+const COMPRESSED_RED_RGTC1: GLenum = 0x8DBB;
+const COMPRESSED_RG_RGTC2: GLenum = 0x8DBD;
+const COMPRESSED_RGBA_BPTC_UNORM: GLenum = 0x8E8C;
+const COMPRESSED_SRGB_ALPHA_BPTC_UNORM: GLenum = 0x8E8D;
+const COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT: GLenum = 0x8E8F;
+const COMPRESSED_RGB8_ETC2: GLenum = 0x9274;
+const COMPRESSED_SRGB8_ETC2: GLenum = 0x9275;
+const COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2: GLenum = 0x9276;
+const COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2: GLenum = 0x9277;
+const COMPRESSED_RGBA8_ETC2_EAC: GLenum = 0x9278;
+const COMPRESSED_SRGB8_ALPHA8_ETC2_EAC: GLenum = 0x9279;
+const COMPRESSED_RGBA_ASTC_4X4: GLenum = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: GLenum = 0x93D0;
+
+// The ASTC block sizes in the order of their format enums.
+const ASTC_BLOCKS: [[u8; 2]; 14] = [
+    [4, 4],
+    [5, 4],
+    [5, 5],
+    [6, 5],
+    [6, 6],
+    [8, 5],
+    [8, 6],
+    [8, 8],
+    [10, 5],
+    [10, 6],
+    [10, 8],
+    [10, 10],
+    [12, 10],
+    [12, 12],
+];
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+
+/// A block compressed pixel format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressedFormat {
+    /// BC1 or DXT1, RGB with 1 bit alpha.
+    Bc1,
+    /// BC2 or DXT3, RGBA with explicit alpha.
+    Bc2,
+    /// BC3 or DXT5, RGBA with interpolated alpha.
+    Bc3,
+    /// BC4, a single channel.
+    Bc4,
+    /// BC5, two channels, common for normal maps.
+    Bc5,
+    /// BC6H, RGB with unsigned half floats.
+    Bc6h,
+    /// BC7, high quality RGBA.
+    Bc7,
+    /// ETC2 RGB.
+    Etc2Rgb,
+    /// ETC2 RGB with 1 bit alpha.
+    Etc2RgbA1,
+    /// ETC2 RGBA with EAC alpha.
+    Etc2Rgba,
+    /// ASTC LDR with a block size of `[width, height]` pixels.
+    Astc([u8; 2]),
+}
+
+impl CompressedFormat {
+    /// Gets the size of a block in pixels.
+    pub fn block_size(&self) -> [u32; 2] {
+        match *self {
+            CompressedFormat::Astc([w, h]) => [w as u32, h as u32],
+            _ => [4, 4],
+        }
+    }
+
+    /// Gets the number of bytes per block.
+    pub fn block_bytes(&self) -> usize {
+        match *self {
+            CompressedFormat::Bc1
+            | CompressedFormat::Bc4
+            | CompressedFormat::Etc2Rgb
+            | CompressedFormat::Etc2RgbA1 => 8,
+            _ => 16,
+        }
+    }
+
+    /// Gets the number of bytes of an image.
+    ///
+    /// Returns `None` if the size does not fit in `usize`.
+    pub fn image_bytes(&self, width: u32, height: u32) -> Option<usize> {
+        let [w, h] = self.block_size();
+        let blocks = width.div_ceil(w) as u64 * height.div_ceil(h) as u64;
+        usize::try_from(blocks.checked_mul(self.block_bytes() as u64)?).ok()
+    }
+
+    /// Returns `true` if the current OpenGL context can sample this format.
+    ///
+    /// Checks the extensions of the format,
+    /// and the compressed formats listed by the context.
+    pub fn is_supported(&self) -> bool {
+        if has_extension(self.extensions()) {
+            return true;
+        }
+
+        // Formats that are core in OpenGL ES 3 have no extension.
+        let mut count: GLint = 0;
+        unsafe {
+            gl_check!(GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count));
+        }
+        let mut formats = vec![0; count as usize];
+        if count > 0 {
+            unsafe {
+                gl_check!(GetIntegerv(
+                    gl::COMPRESSED_TEXTURE_FORMATS,
+                    formats.as_mut_ptr()
+                ));
+            }
+        }
+        formats.contains(&(self.gl_format(false) as GLint))
+    }
+
+    /// Gets the extensions providing the format, for desktop, mobile and WebGL.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match *self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc2 | CompressedFormat::Bc3 => &[
+                "GL_EXT_texture_compression_s3tc",
+                "WEBGL_compressed_texture_s3tc",
+            ],
+            CompressedFormat::Bc4 | CompressedFormat::Bc5 => &[
+                "GL_ARB_texture_compression_rgtc",
+                "GL_EXT_texture_compression_rgtc",
+                "EXT_texture_compression_rgtc",
+            ],
+            CompressedFormat::Bc6h | CompressedFormat::Bc7 => &[
+                "GL_ARB_texture_compression_bptc",
+                "GL_EXT_texture_compression_bptc",
+                "EXT_texture_compression_bptc",
+            ],
+            CompressedFormat::Etc2Rgb
+            | CompressedFormat::Etc2RgbA1
+            | CompressedFormat::Etc2Rgba => {
+                &["GL_ARB_ES3_compatibility", "WEBGL_compressed_texture_etc"]
+            }
+            CompressedFormat::Astc(_) => &[
+                "GL_KHR_texture_compression_astc_ldr",
+                "WEBGL_compressed_texture_astc",
+            ],
+        }
+    }
+
+    // Gets the internal format, using the sRGB variant if there is one.
+    pub(crate) fn gl_format(&self, srgb: bool) -> GLenum {
+        let (linear, srgb_format) = match *self {
+            CompressedFormat::Bc1 => (COMPRESSED_RGBA_S3TC_DXT1, COMPRESSED_SRGB_ALPHA_S3TC_DXT1),
+            CompressedFormat::Bc2 => (COMPRESSED_RGBA_S3TC_DXT3, COMPRESSED_SRGB_ALPHA_S3TC_DXT3),
+            CompressedFormat::Bc3 => (COMPRESSED_RGBA_S3TC_DXT5, COMPRESSED_SRGB_ALPHA_S3TC_DXT5),
+            CompressedFormat::Bc4 => (COMPRESSED_RED_RGTC1, COMPRESSED_RED_RGTC1),
+            CompressedFormat::Bc5 => (COMPRESSED_RG_RGTC2, COMPRESSED_RG_RGTC2),
+            CompressedFormat::Bc6h => (
+                COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+                COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            ),
+            CompressedFormat::Bc7 => (COMPRESSED_RGBA_BPTC_UNORM, COMPRESSED_SRGB_ALPHA_BPTC_UNORM),
+            CompressedFormat::Etc2Rgb => (COMPRESSED_RGB8_ETC2, COMPRESSED_SRGB8_ETC2),
+            CompressedFormat::Etc2RgbA1 => (
+                COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+                COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            ),
+            CompressedFormat::Etc2Rgba => {
+                (COMPRESSED_RGBA8_ETC2_EAC, COMPRESSED_SRGB8_ALPHA8_ETC2_EAC)
+            }
+            CompressedFormat::Astc(block) => {
+                let i = ASTC_BLOCKS.iter().position(|&b| b == block).unwrap_or(0) as GLenum;
+                (
+                    COMPRESSED_RGBA_ASTC_4X4 + i,
+                    COMPRESSED_SRGB8_ALPHA8_ASTC_4X4 + i,
+                )
+            }
+        };
+        if srgb {
+            srgb_format
+        } else {
+            linear
+        }
+    }
+}
+
+/// The mip levels of a compressed image, borrowed from a container.
+pub(crate) struct CompressedImage<'a> {
+    pub format: CompressedFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The levels from the full size image down.
+    pub levels: Vec<&'a [u8]>,
+}
+
+/// Parses a KTX2 or DDS container.
+pub(crate) fn parse(bytes: &[u8]) -> Result<CompressedImage<'_>, Error> {
+    if bytes.starts_with(&KTX2_IDENTIFIER) {
+        parse_ktx2(bytes)
+    } else if bytes.starts_with(DDS_MAGIC) {
+        parse_dds(bytes)
+    } else {
+        Err(Error::UnsupportedFormat(
+            "Unknown container, expected KTX2 or DDS".into(),
+        ))
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, Error> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(Error::Texture("Unexpected end of container".into())),
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<usize, Error> {
+    let low = read_u32(bytes, offset)? as u64;
+    let high = read_u32(bytes, offset + 4)? as u64;
+    usize::try_from(high << 32 | low)
+        .map_err(|_| Error::Texture("Container offset does not fit in memory".into()))
+}
+
+// Gets the number of bytes of a mip level.
+fn level_bytes(
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    level: u32,
+) -> Result<usize, Error> {
+    let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+    format
+        .image_bytes(width, height)
+        .ok_or_else(|| Error::Texture(format!("{}x{} pixels do not fit in memory", width, height)))
+}
+
+// Checks that an image is not empty.
+fn check_nonzero(width: u32, height: u32) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        return Err(Error::Texture(format!(
+            "Expected a non-empty image, got {}x{} pixels",
+            width, height
+        )));
+    }
+    Ok(())
+}
+
+fn parse_ktx2(bytes: &[u8]) -> Result<CompressedImage<'_>, Error> {
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    // A height of 0 marks a 1D texture.
+    let height = read_u32(bytes, 24)?.max(1);
+    let depth = read_u32(bytes, 28)?;
+    let layers = read_u32(bytes, 32)?;
+    let faces = read_u32(bytes, 36)?;
+    let level_count = read_u32(bytes, 40)?.max(1);
+    let supercompression = read_u32(bytes, 44)?;
+    if supercompression != 0 {
+        return Err(Error::UnsupportedFormat(format!(
+            "KTX2 supercompression scheme {} is not supported",
+            supercompression
+        )));
+    }
+    if depth > 1 || layers > 1 || faces > 1 {
+        return Err(Error::UnsupportedFormat(
+            "Only 2D KTX2 textures are supported".into(),
+        ));
+    }
+    let format = match vk_format {
+        131..=134 => CompressedFormat::Bc1,
+        135 | 136 => CompressedFormat::Bc2,
+        137 | 138 => CompressedFormat::Bc3,
+        139 => CompressedFormat::Bc4,
+        141 => CompressedFormat::Bc5,
+        143 => CompressedFormat::Bc6h,
+        145 | 146 => CompressedFormat::Bc7,
+        147 | 148 => CompressedFormat::Etc2Rgb,
+        149 | 150 => CompressedFormat::Etc2RgbA1,
+        151 | 152 => CompressedFormat::Etc2Rgba,
+        157..=184 => CompressedFormat::Astc(ASTC_BLOCKS[(vk_format as usize - 157) / 2]),
+        x => {
+            return Err(Error::UnsupportedFormat(format!(
+                "Unsupported KTX2 Vulkan format {}",
+                x
+            )))
+        }
+    };
+
+    // The level index follows the 80 byte header, starting at the full size image.
+    let mut levels = vec![];
+    check_nonzero(width, height)?;
+    check_levels(level_count, width, height)?;
+    for i in 0..level_count {
+        let entry = 80 + i as usize * 24;
+        let offset = read_u64(bytes, entry)?;
+        let len = read_u64(bytes, entry + 8)?;
+        let expected = level_bytes(format, width, height, i)?;
+        match bytes.get(offset..offset.saturating_add(len)) {
+            Some(data) if len >= expected => levels.push(&data[..expected]),
+            _ => return Err(Error::Texture(format!("KTX2 level {} is out of bounds", i))),
+        }
+    }
+    Ok(CompressedImage {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<CompressedImage<'_>, Error> {
+    // The header follows the magic number.
+    let flags = read_u32(bytes, 8)?;
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let mip_count = if flags & 0x20000 != 0 {
+        read_u32(bytes, 28)?.max(1)
+    } else {
+        1
+    };
+    let caps2 = read_u32(bytes, 112)?;
+    if caps2 & 0x200 != 0 {
+        return Err(Error::UnsupportedFormat(
+            "DDS cube maps are not supported".into(),
+        ));
+    }
+    let four_cc = bytes.get(84..88).unwrap_or(&[]);
+    let (format, mut offset): (_, usize) = match four_cc {
+        b"DXT1" => (CompressedFormat::Bc1, 128),
+        b"DXT2" | b"DXT3" => (CompressedFormat::Bc2, 128),
+        b"DXT4" | b"DXT5" => (CompressedFormat::Bc3, 128),
+        b"ATI1" | b"BC4U" => (CompressedFormat::Bc4, 128),
+        b"ATI2" | b"BC5U" => (CompressedFormat::Bc5, 128),
+        b"DX10" => {
+            let format = match read_u32(bytes, 128)? {
+                71 | 72 => CompressedFormat::Bc1,
+                74 | 75 => CompressedFormat::Bc2,
+                77 | 78 => CompressedFormat::Bc3,
+                80 => CompressedFormat::Bc4,
+                83 => CompressedFormat::Bc5,
+                95 => CompressedFormat::Bc6h,
+                98 | 99 => CompressedFormat::Bc7,
+                x => {
+                    return Err(Error::UnsupportedFormat(format!(
+                        "Unsupported DDS DXGI format {}",
+                        x
+                    )))
+                }
+            };
+            if read_u32(bytes, 140)? > 1 {
+                return Err(Error::UnsupportedFormat(
+                    "DDS texture arrays are not supported".into(),
+                ));
+            }
+            (format, 148)
+        }
+        x => {
+            return Err(Error::UnsupportedFormat(format!(
+                "Unsupported DDS format {:?}",
+                x
+            )))
+        }
+    };
+
+    // The levels are stored one after another.
+    let mut levels = vec![];
+    check_nonzero(width, height)?;
+    check_levels(mip_count, width, height)?;
+    for i in 0..mip_count {
+        let len = level_bytes(format, width, height, i)?;
+        let data = offset
+            .checked_add(len)
+            .and_then(|end| bytes.get(offset..end));
+        match data {
+            Some(data) => levels.push(data),
+            None => return Err(Error::Texture(format!("DDS level {} is out of bounds", i))),
+        }
+        offset += len;
+    }
+    Ok(CompressedImage {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+#[test]
+fn test_parse_dds() {
+    // An 8x8 BC1 image with two levels.
+    let mut dds = vec![0u8; 128];
+    dds[..4].copy_from_slice(DDS_MAGIC);
+    dds[8..12].copy_from_slice(&0x20000u32.to_le_bytes());
+    dds[12..16].copy_from_slice(&8u32.to_le_bytes());
+    dds[16..20].copy_from_slice(&8u32.to_le_bytes());
+    dds[28..32].copy_from_slice(&2u32.to_le_bytes());
+    dds[84..88].copy_from_slice(b"DXT1");
+    dds.extend_from_slice(&[1; 32]);
+    dds.extend_from_slice(&[2; 8]);
+    let image = parse(&dds).unwrap();
+    assert_eq!(image.format, CompressedFormat::Bc1);
+    assert_eq!((image.width, image.height), (8, 8));
+    assert_eq!(image.levels, vec![&[1; 32][..], &[2; 8][..]]);
+
+    dds.truncate(150);
+    assert!(parse(&dds).is_err());
+}
+
+#[test]
+fn test_parse_dds_size() {
+    // A BC2 image with a header claiming more data than fits in memory.
+    let mut dds = vec![0u8; 256];
+    dds[..4].copy_from_slice(DDS_MAGIC);
+    dds[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    dds[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    dds[84..88].copy_from_slice(b"DXT3");
+    assert!(parse(&dds).is_err());
+
+    dds[16..20].copy_from_slice(&0u32.to_le_bytes());
+    assert!(parse(&dds).is_err());
+}
+
+#[test]
+fn test_parse_ktx2_levels() {
+    // An 8x8 BC1 image claiming 40 levels, which all point to valid data.
+    let data = 80 + 40 * 24;
+    let mut ktx2 = vec![0u8; data + 32];
+    ktx2[..12].copy_from_slice(&KTX2_IDENTIFIER);
+    ktx2[12..16].copy_from_slice(&131u32.to_le_bytes());
+    ktx2[20..24].copy_from_slice(&8u32.to_le_bytes());
+    ktx2[24..28].copy_from_slice(&8u32.to_le_bytes());
+    ktx2[40..44].copy_from_slice(&40u32.to_le_bytes());
+    for i in 0..40 {
+        let entry = 80 + i * 24;
+        ktx2[entry..entry + 8].copy_from_slice(&(data as u64).to_le_bytes());
+        ktx2[entry + 8..entry + 16].copy_from_slice(&32u64.to_le_bytes());
+    }
+    assert!(parse(&ktx2).is_err());
+
+    ktx2[40..44].copy_from_slice(&4u32.to_le_bytes());
+    assert_eq!(parse(&ktx2).unwrap().levels.len(), 4);
+}
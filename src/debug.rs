@@ -0,0 +1,238 @@
+//! Debug output through `KHR_debug` and checked OpenGL calls.
+//!
+//! Enable object labels before creating `GlGraphics` and textures,
+//! so the objects show up with readable names in tools like RenderDoc.
+//!
+//! For drivers without `KHR_debug`, checked mode calls `glGetError`
+//! after every OpenGL call made by this crate and collects the failures.
+
+/// Calls an OpenGL function, followed by `glGetError` in checked mode.
+macro_rules! gl_check {
+    ($name:ident($($arg:expr),* $(,)?)) => {
+        $crate::debug::checked(stringify!($name), gl::$name($($arg),*))
+    };
+}
+
+// External crates.
+use gl::types::{GLenum, GLuint};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// Local crate.
+use crate::error::Error;
+
+#[cfg(not(feature = "glow"))]
+use gl::types::{GLchar, GLsizei};
+#[cfg(not(feature = "glow"))]
+use std::ffi::{c_void, CStr};
+#[cfg(not(feature = "glow"))]
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(not(feature = "glow"))]
+use std::{ptr, slice};
+
+type Callback = Box<dyn FnMut(&DebugMessage) + Send>;
+
+static CALLBACK: Mutex<Option<Callback>> = Mutex::new(None);
+static OBJECT_LABELS: AtomicBool = AtomicBool::new(false);
+static CHECKED: AtomicBool = AtomicBool::new(false);
+static ERRORS: Mutex<Vec<Error>> = Mutex::new(Vec::new());
+
+// The maximum number of errors kept until `take_errors` is called.
+const MAX_ERRORS: usize = 64;
+
+/// The severity of a debug message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Anything that is not an error or performance issue.
+    Notification,
+    /// Redundant state changes or unimportant undefined behavior.
+    Low,
+    /// Major performance warnings or use of deprecated functionality.
+    Medium,
+    /// Errors or undefined behavior.
+    High,
+}
+
+/// A message reported by the driver.
+#[derive(Clone, Copy, Debug)]
+pub struct DebugMessage<'a> {
+    /// The source, for example `DEBUG_SOURCE_API`.
+    pub source: GLenum,
+    /// The type, for example `DEBUG_TYPE_ERROR`.
+    pub ty: GLenum,
+    /// The driver specific message id.
+    pub id: GLuint,
+    /// The severity.
+    pub severity: Severity,
+    /// The message text.
+    pub message: &'a str,
+}
+
+/// Installs a callback receiving the driver's debug messages.
+///
+/// Returns `false` if `glDebugMessageCallback` is not available.
+/// With the `glow` feature the callback must be installed on the
+/// `glow::Context` before calling `set_context`,
+/// forwarding the messages to `handle_message`.
+pub fn set_message_callback<F>(callback: F) -> bool
+where
+    F: FnMut(&DebugMessage) + Send + 'static,
+{
+    *CALLBACK.lock().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(callback));
+    install()
+}
+
+/// Routes the driver's debug messages into the `log` crate.
+///
+/// Returns `false` if `glDebugMessageCallback` is not available.
+#[cfg(feature = "log")]
+pub fn set_message_log() -> bool {
+    set_message_callback(|msg: &DebugMessage| {
+        let level = match msg.severity {
+            Severity::High => log::Level::Error,
+            Severity::Medium => log::Level::Warn,
+            Severity::Low => log::Level::Info,
+            Severity::Notification => log::Level::Debug,
+        };
+        log::log!(target: "opengl_graphics", level, "{}", msg.message);
+    })
+}
+
+/// Passes a debug message to the installed callback.
+pub fn handle_message(source: GLenum, ty: GLenum, id: GLuint, severity: GLenum, message: &str) {
+    let severity = match severity {
+        gl::DEBUG_SEVERITY_HIGH => Severity::High,
+        gl::DEBUG_SEVERITY_MEDIUM => Severity::Medium,
+        gl::DEBUG_SEVERITY_LOW => Severity::Low,
+        _ => Severity::Notification,
+    };
+    let msg = DebugMessage {
+        source,
+        ty,
+        id,
+        severity,
+        message,
+    };
+    // Messages emitted while the callback runs are dropped instead of deadlocking.
+    if let Ok(mut callback) = CALLBACK.try_lock() {
+        if let Some(ref mut f) = *callback {
+            f(&msg);
+        }
+    }
+}
+
+/// Sets whether OpenGL objects created by this crate get labels.
+pub fn set_object_labels(enabled: bool) {
+    OBJECT_LABELS.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if OpenGL objects created by this crate get labels.
+pub fn object_labels() -> bool {
+    OBJECT_LABELS.load(Ordering::Relaxed)
+}
+
+/// Sets whether every OpenGL call made by this crate is checked with `glGetError`.
+///
+/// This is slow and meant for debugging drivers without `KHR_debug`.
+pub fn set_checked(enabled: bool) {
+    CHECKED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns `true` if OpenGL calls are checked with `glGetError`.
+pub fn is_checked() -> bool {
+    CHECKED.load(Ordering::Relaxed)
+}
+
+/// Returns the errors collected in checked mode since the last call.
+///
+/// At most 64 errors are kept, later ones are dropped.
+pub fn take_errors() -> Vec<Error> {
+    let mut errors = ERRORS.lock().unwrap_or_else(|err| err.into_inner());
+    ::std::mem::take(&mut *errors)
+}
+
+// Error code reported by WebGL after a `webglcontextlost` event.
+const CONTEXT_LOST_WEBGL: GLenum = 0x9242;
+
+// Passes through the result of an OpenGL call, collecting errors in checked mode.
+#[doc(hidden)]
+pub fn checked<T>(function: &'static str, res: T) -> T {
+    if !is_checked() {
+        return res;
+    }
+    let mut errors = ERRORS.lock().unwrap_or_else(|err| err.into_inner());
+    loop {
+        let code = unsafe { gl::GetError() };
+        if code == gl::NO_ERROR {
+            break;
+        }
+        if errors.len() < MAX_ERRORS {
+            errors.push(Error::Gl { function, code });
+        }
+        // A lost context keeps reporting errors.
+        if code == gl::CONTEXT_LOST || code == CONTEXT_LOST_WEBGL {
+            break;
+        }
+    }
+    res
+}
+
+/// Labels an OpenGL object, if object labels are enabled.
+pub(crate) fn label(identifier: GLenum, name: GLuint, label: &str) {
+    if !object_labels() || !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(
+            identifier,
+            name,
+            label.len() as i32,
+            label.as_ptr() as *const _,
+        );
+    }
+}
+
+#[cfg(not(feature = "glow"))]
+fn install() -> bool {
+    if !gl::DebugMessageCallback::is_loaded() {
+        return false;
+    }
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        // Report messages on the thread making the GL call.
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(message_callback), ptr::null());
+    }
+    true
+}
+
+#[cfg(feature = "glow")]
+fn install() -> bool {
+    // glow needs mutable access to install a callback,
+    // which is not available once the context is shared.
+    false
+}
+
+#[cfg(not(feature = "glow"))]
+extern "system" fn message_callback(
+    source: GLenum,
+    ty: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let bytes = unsafe {
+        if length < 0 {
+            CStr::from_ptr(message).to_bytes()
+        } else {
+            slice::from_raw_parts(message as *const u8, length as usize)
+        }
+    };
+    // Unwinding out of an `extern` function aborts, so the panic is dropped here.
+    let message = String::from_utf8_lossy(bytes);
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        handle_message(source, ty, id, severity, &message)
+    }));
+}
@@ -29,16 +29,16 @@ pub fn bind_scissor(rect: Option<[u32; 4]>, viewport: &Option<Viewport>) {
                 r[1] as i32
             };
             unsafe {
-                gl::Enable(gl::SCISSOR_TEST);
-                gl::Scissor(
+                gl_check!(Enable(gl::SCISSOR_TEST));
+                gl_check!(Scissor(
                     r[0] as gl::types::GLint,
                     yb as gl::types::GLint,
                     r[2] as gl::types::GLint,
                     r[3] as gl::types::GLint,
-                );
+                ));
             }
         }
-        None => unsafe { gl::Disable(gl::SCISSOR_TEST) },
+        None => unsafe { gl_check!(Disable(gl::SCISSOR_TEST)) },
     }
 }
 
@@ -46,31 +46,31 @@ pub fn bind_stencil(stencil: Option<Stencil>) {
     unsafe {
         match stencil {
             Some(s) => {
-                gl::Enable(gl::STENCIL_TEST);
+                gl_check!(Enable(gl::STENCIL_TEST));
                 match s {
                     Stencil::Increment => {
-                        gl::StencilFunc(gl::NEVER, 0 as gl::types::GLint, 255);
-                        gl::StencilMask(255);
-                        gl::StencilOp(gl::INCR, gl::KEEP, gl::KEEP);
+                        gl_check!(StencilFunc(gl::NEVER, 0 as gl::types::GLint, 255));
+                        gl_check!(StencilMask(255));
+                        gl_check!(StencilOp(gl::INCR, gl::KEEP, gl::KEEP));
                     }
                     Stencil::Clip(val) => {
-                        gl::StencilFunc(gl::NEVER, val as gl::types::GLint, 255);
-                        gl::StencilMask(255);
-                        gl::StencilOp(gl::REPLACE, gl::KEEP, gl::KEEP);
+                        gl_check!(StencilFunc(gl::NEVER, val as gl::types::GLint, 255));
+                        gl_check!(StencilMask(255));
+                        gl_check!(StencilOp(gl::REPLACE, gl::KEEP, gl::KEEP));
                     }
                     Stencil::Inside(val) => {
-                        gl::StencilFunc(gl::EQUAL, val as gl::types::GLint, 255);
-                        gl::StencilMask(255);
-                        gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+                        gl_check!(StencilFunc(gl::EQUAL, val as gl::types::GLint, 255));
+                        gl_check!(StencilMask(255));
+                        gl_check!(StencilOp(gl::KEEP, gl::KEEP, gl::KEEP));
                     }
                     Stencil::Outside(val) => {
-                        gl::StencilFunc(gl::NOTEQUAL, val as gl::types::GLint, 255);
-                        gl::StencilMask(255);
-                        gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+                        gl_check!(StencilFunc(gl::NOTEQUAL, val as gl::types::GLint, 255));
+                        gl_check!(StencilMask(255));
+                        gl_check!(StencilOp(gl::KEEP, gl::KEEP, gl::KEEP));
                     }
                 }
             }
-            None => gl::Disable(gl::STENCIL_TEST),
+            None => gl_check!(Disable(gl::STENCIL_TEST)),
         }
     }
 }
@@ -111,37 +111,47 @@ pub fn bind_blend(blend: Option<Blend>) {
     unsafe {
         match blend {
             Some(b) => {
-                gl::Enable(gl::BLEND);
-                gl::BlendColor(1.0, 1.0, 1.0, 1.0);
+                gl_check!(Enable(gl::BLEND));
+                gl_check!(BlendColor(1.0, 1.0, 1.0, 1.0));
                 match b {
                     Blend::Alpha => {
-                        gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
-                        gl::BlendFuncSeparate(
+                        gl_check!(BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD));
+                        gl_check!(BlendFuncSeparate(
                             gl::SRC_ALPHA,
                             gl::ONE_MINUS_SRC_ALPHA,
                             gl::ONE,
                             gl::ONE,
-                        );
+                        ));
                     }
                     Blend::Add => {
-                        gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
-                        gl::BlendFuncSeparate(gl::ONE, gl::ONE, gl::ONE, gl::ONE);
+                        gl_check!(BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD));
+                        gl_check!(BlendFuncSeparate(gl::ONE, gl::ONE, gl::ONE, gl::ONE));
                     }
                     Blend::Lighter => {
-                        gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
-                        gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE, gl::ZERO, gl::ONE);
+                        gl_check!(BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD));
+                        gl_check!(BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE, gl::ZERO, gl::ONE));
                     }
                     Blend::Multiply => {
-                        gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
-                        gl::BlendFuncSeparate(gl::DST_COLOR, gl::ZERO, gl::DST_ALPHA, gl::ZERO);
+                        gl_check!(BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD));
+                        gl_check!(BlendFuncSeparate(
+                            gl::DST_COLOR,
+                            gl::ZERO,
+                            gl::DST_ALPHA,
+                            gl::ZERO
+                        ));
                     }
                     Blend::Invert => {
-                        gl::BlendEquationSeparate(gl::FUNC_SUBTRACT, gl::FUNC_ADD);
-                        gl::BlendFuncSeparate(gl::CONSTANT_COLOR, gl::SRC_COLOR, gl::ZERO, gl::ONE);
+                        gl_check!(BlendEquationSeparate(gl::FUNC_SUBTRACT, gl::FUNC_ADD));
+                        gl_check!(BlendFuncSeparate(
+                            gl::CONSTANT_COLOR,
+                            gl::SRC_COLOR,
+                            gl::ZERO,
+                            gl::ONE
+                        ));
                     }
                 }
             }
-            None => gl::Disable(gl::BLEND),
+            None => gl_check!(Disable(gl::BLEND)),
         }
     }
 }
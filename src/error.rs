@@ -1,17 +1,138 @@
 //! Errors
 
+use gl::types::GLenum;
+use shader_version::glsl::GLSL;
+use std::error;
 use std::fmt;
+use std::path::PathBuf;
+
+/// The stage of a shader in a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    /// A vertex shader.
+    Vertex,
+    /// A fragment shader.
+    Fragment,
+    /// Another kind of shader, by its OpenGL enum.
+    Other(GLenum),
+}
+
+impl ShaderStage {
+    /// Gets the stage of an OpenGL shader type.
+    pub fn from_gl(shader_type: GLenum) -> ShaderStage {
+        match shader_type {
+            gl::VERTEX_SHADER => ShaderStage::Vertex,
+            gl::FRAGMENT_SHADER => ShaderStage::Fragment,
+            x => ShaderStage::Other(x),
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+            ShaderStage::Other(x) => write!(f, "{:#x}", x),
+        }
+    }
+}
 
 /// An enum to represent various possible run-time errors that may occur.
 #[derive(Debug)]
 pub enum Error {
     /// An error happened with I/O.
     IoError(::std::io::Error),
+    /// An image could not be decoded.
+    Image {
+        /// The file the image was loaded from, if any.
+        path: Option<PathBuf>,
+        /// The error of the decoder.
+        source: image::ImageError,
+    },
+    /// The OpenGL context was lost, for example by a `webglcontextlost` event
+    /// or a graphics reset.
+    ///
+    /// All GL objects are invalid until the context is restored.
+    ContextLost,
+    /// The OpenGL functions are not loaded, because there is no context yet.
+    ContextMissing,
+    /// A shader failed to compile.
+    ShaderCompile {
+        /// The stage of the shader.
+        stage: ShaderStage,
+        /// The info log of the compiler.
+        log: String,
+    },
+    /// No shader was given for the GLSL version of the context.
+    MissingShader {
+        /// The stage of the shader.
+        stage: ShaderStage,
+        /// The GLSL version of the context.
+        glsl: GLSL,
+    },
+    /// A program failed to link.
+    ProgramLink {
+        /// The info log of the linker.
+        log: String,
+    },
+    /// An attribute does not exist in a program.
+    MissingAttribute(String),
+    /// A uniform does not exist in a program.
+    MissingUniform(String),
+    /// A format is not supported by the context or this library.
+    UnsupportedFormat(String),
+    /// A texture could not be created or updated from the given data.
+    Texture(String),
+    /// The texture has no retained CPU data to restore from.
+    NotRetained,
+    /// An OpenGL call reported an error in checked mode.
+    Gl {
+        /// The name of the OpenGL function, for example `TexImage2D`.
+        function: &'static str,
+        /// The error code returned by `glGetError`.
+        code: u32,
+    },
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(self, f)
+        match *self {
+            Error::IoError(ref err) => write!(f, "I/O error: {}", err),
+            Error::Image {
+                path: Some(ref path),
+                ..
+            } => write!(f, "Could not load image '{}'", path.display()),
+            Error::Image { path: None, .. } => write!(f, "Could not load image from bytes"),
+            Error::ContextLost => write!(f, "The OpenGL context was lost"),
+            Error::ContextMissing => write!(f, "The OpenGL functions are not loaded"),
+            Error::ShaderCompile { stage, ref log } => {
+                write!(f, "Error compiling {} shader: {}", stage, log)
+            }
+            Error::MissingShader { stage, glsl } => {
+                write!(f, "No compatible {} shader for {:?}", stage, glsl)
+            }
+            Error::ProgramLink { ref log } => write!(f, "Error linking program: {}", log),
+            Error::MissingAttribute(ref name) => {
+                write!(f, "Attribute '{}' does not exists in shader", name)
+            }
+            Error::MissingUniform(ref name) => {
+                write!(f, "Uniform '{}' does not exists in shader", name)
+            }
+            Error::UnsupportedFormat(ref msg) | Error::Texture(ref msg) => write!(f, "{}", msg),
+            Error::NotRetained => write!(f, "The texture has no retained data"),
+            Error::Gl { function, code } => write!(f, "{} failed with error {:#x}", function, code),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::IoError(ref err) => Some(err),
+            Error::Image { ref source, .. } => Some(source),
+            _ => None,
+        }
     }
 }
 
@@ -0,0 +1,150 @@
+//! Deferred deletion of OpenGL objects.
+//!
+//! Dropping an object only queues its id, so it can happen on any thread
+//! or after the context is gone.
+//! `GlGraphics` deletes the queued objects on the OpenGL thread.
+//! Textures with triangles batched by any `GlGraphics` are pinned,
+//! and stay queued until the batch is flushed,
+//! so their ids are not deleted or reused by new textures while still in use.
+//!
+//! There is one queue for the process, so objects must belong to a single context,
+//! or to contexts sharing their objects.
+//! The queue only shrinks when `GlGraphics` draws or collects garbage.
+
+use gl::types::{GLsync, GLuint};
+use std::mem;
+use std::sync::{Mutex, MutexGuard};
+
+/// An OpenGL object waiting to be deleted.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Garbage {
+    Texture(GLuint),
+    Buffer(GLuint),
+    VertexArray(GLuint),
+    Program(GLuint),
+    Shader(GLuint),
+    Sampler(GLuint),
+    Query(GLuint),
+    // A fence stored as an address, so the queue can be shared between threads.
+    Sync(usize),
+}
+
+// Objects waiting to be deleted, and the textures still used by batches.
+struct Queue {
+    items: Vec<Garbage>,
+    // Texture ids, once for every batch using them.
+    pinned: Vec<GLuint>,
+}
+
+impl Queue {
+    const fn new() -> Self {
+        Queue {
+            items: Vec::new(),
+            pinned: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, garbage: Garbage) {
+        match garbage {
+            Garbage::Texture(0)
+            | Garbage::Buffer(0)
+            | Garbage::VertexArray(0)
+            | Garbage::Program(0)
+            | Garbage::Shader(0)
+            | Garbage::Sampler(0)
+            | Garbage::Query(0)
+            | Garbage::Sync(0) => {}
+            _ => self.items.push(garbage),
+        }
+    }
+
+    fn pin(&mut self, id: GLuint) {
+        if id != 0 {
+            self.pinned.push(id);
+        }
+    }
+
+    fn unpin(&mut self, id: GLuint) {
+        if let Some(i) = self.pinned.iter().position(|&pinned| pinned == id) {
+            self.pinned.swap_remove(i);
+        }
+    }
+
+    // Takes the objects that can be deleted, keeping pinned textures queued.
+    fn take(&mut self) -> Vec<Garbage> {
+        let pinned = &self.pinned;
+        let (kept, taken) = mem::take(&mut self.items)
+            .into_iter()
+            .partition(|item| matches!(*item, Garbage::Texture(id) if pinned.contains(&id)));
+        self.items = kept;
+        taken
+    }
+}
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue::new());
+
+fn queue() -> MutexGuard<'static, Queue> {
+    // The queue holds plain ids, so it is never left half updated.
+    QUEUE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Queues an object for deletion, ignoring the id `0`.
+pub(crate) fn delete(garbage: Garbage) {
+    queue().push(garbage);
+}
+
+/// Keeps a texture from being deleted while triangles using it are batched.
+pub(crate) fn pin_texture(id: GLuint) {
+    queue().pin(id);
+}
+
+/// Releases a texture pinned with `pin_texture`, after its batch was flushed.
+pub(crate) fn unpin_texture(id: GLuint) {
+    queue().unpin(id);
+}
+
+/// Deletes the queued objects, except textures that are pinned.
+///
+/// Must be called on the thread of the current OpenGL context.
+pub(crate) fn collect() {
+    let garbage = queue().take();
+    for item in garbage {
+        unsafe {
+            match item {
+                Garbage::Texture(id) => gl_check!(DeleteTextures(1, &id)),
+                Garbage::Buffer(id) => gl_check!(DeleteBuffers(1, &id)),
+                Garbage::VertexArray(id) => gl_check!(DeleteVertexArrays(1, &id)),
+                Garbage::Program(id) => gl_check!(DeleteProgram(id)),
+                Garbage::Shader(id) => gl_check!(DeleteShader(id)),
+                Garbage::Sampler(id) => gl_check!(DeleteSamplers(1, &id)),
+                Garbage::Query(id) => gl_check!(DeleteQueries(1, &id)),
+                Garbage::Sync(fence) => gl_check!(DeleteSync(fence as GLsync)),
+            }
+        }
+    }
+}
+
+/// Forgets the queued objects, which belong to a lost context.
+pub(crate) fn discard() {
+    queue().items.clear();
+}
+
+#[test]
+fn test_pinned() {
+    // A texture dropped while batched, next to a buffer dropped at the same time.
+    let mut queue = Queue::new();
+    queue.push(Garbage::Texture(0));
+    assert!(queue.items.is_empty());
+    queue.pin(7);
+    queue.push(Garbage::Texture(7));
+    queue.push(Garbage::Buffer(8));
+    let taken = queue.take();
+    assert!(matches!(taken[..], [Garbage::Buffer(8)]));
+    assert!(matches!(queue.items[..], [Garbage::Texture(7)]));
+
+    // The texture is deleted once the batch is flushed.
+    queue.unpin(7);
+    let taken = queue.take();
+    assert!(matches!(taken[..], [Garbage::Texture(7)]));
+    assert!(queue.items.is_empty());
+}
@@ -0,0 +1,225 @@
+//! GPU timer queries for frame profiling.
+
+use gl::types::{GLenum, GLint, GLuint};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::back_end::has_extension;
+use crate::garbage::{self, Garbage};
+
+// The number of frames waiting for results before new frames are skipped,
+// so reading the results never stalls the pipeline.
+const MAX_PENDING_FRAMES: usize = 3;
+
+// Set after timer queries were disturbed, for example by a change of the GPU clock.
+const GPU_DISJOINT_EXT: GLenum = 0x8FBB;
+
+// The extensions reporting disjoint timer queries through `GPU_DISJOINT_EXT`.
+const DISJOINT_EXTENSIONS: [&str; 2] = [
+    "GL_EXT_disjoint_timer_query",
+    "EXT_disjoint_timer_query_webgl2",
+];
+
+/// Returns `true` if the context supports time elapsed queries.
+pub(crate) fn is_supported() -> bool {
+    let extensions = ["GL_ARB_timer_query", "GL_EXT_timer_query"];
+    gl::GenQueries::is_loaded()
+        && (has_extension(&extensions) || has_extension(&DISJOINT_EXTENSIONS))
+}
+
+/// GPU time spent on a frame, measured with timer queries.
+///
+/// Frames measured while the GPU changed its clock or power state are skipped.
+/// With the `glow` feature, results are read as 32 bit nanoseconds,
+/// so durations above about 4.29 seconds wrap around.
+#[derive(Clone, Debug, Default)]
+pub struct GpuTiming {
+    /// The time spent between `draw_begin` and `draw_end`.
+    pub frame: Duration,
+    /// The time spent in labelled sections, in order.
+    pub sections: Vec<(String, Duration)>,
+}
+
+// A time elapsed query covering a part of a frame.
+struct Segment {
+    query: GLuint,
+    label: Option<String>,
+}
+
+/// Measures frames with a pool of `TIME_ELAPSED` queries.
+///
+/// Time elapsed queries can not be nested,
+/// so a frame is split into consecutive segments at section borders.
+pub(crate) struct GpuTimer {
+    free: Vec<GLuint>,
+    pending: VecDeque<Vec<Segment>>,
+    // The segments of the frame being recorded, `None` if skipped.
+    current: Option<Vec<Segment>>,
+    in_section: bool,
+    latest: Option<GpuTiming>,
+    // Whether `GPU_DISJOINT_EXT` can be queried.
+    has_disjoint: bool,
+    // The number of pending frames with undefined results after a disjoint event.
+    discard: usize,
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        let current = self.current.iter().flatten();
+        let pending = self.pending.iter().flatten();
+        let ids = current
+            .chain(pending)
+            .map(|segment| segment.query)
+            .chain(self.free.iter().cloned());
+        for id in ids {
+            garbage::delete(Garbage::Query(id));
+        }
+    }
+}
+
+impl GpuTimer {
+    pub fn new() -> Self {
+        GpuTimer {
+            free: vec![],
+            pending: VecDeque::new(),
+            current: None,
+            in_section: false,
+            latest: None,
+            has_disjoint: has_extension(&DISJOINT_EXTENSIONS),
+            discard: 0,
+        }
+    }
+
+    /// Returns the latest frame with available results.
+    pub fn latest(&self) -> Option<&GpuTiming> {
+        self.latest.as_ref()
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.poll();
+        if self.current.is_some() || self.pending.len() >= MAX_PENDING_FRAMES {
+            return;
+        }
+        self.current = Some(vec![]);
+        self.begin_segment(None);
+    }
+
+    pub fn end_frame(&mut self) {
+        if let Some(frame) = self.current.take() {
+            unsafe {
+                gl_check!(EndQuery(gl::TIME_ELAPSED));
+            }
+            self.pending.push_back(frame);
+        }
+        self.in_section = false;
+    }
+
+    pub fn begin_section(&mut self, label: &str) {
+        if self.current.is_none() {
+            return;
+        }
+        self.end_segment();
+        self.begin_segment(Some(label.to_string()));
+        self.in_section = true;
+    }
+
+    pub fn end_section(&mut self) {
+        if self.current.is_none() || !self.in_section {
+            return;
+        }
+        self.end_segment();
+        self.begin_segment(None);
+        self.in_section = false;
+    }
+
+    /// Forgets the queries without deleting them.
+    ///
+    /// Used when the OpenGL context was lost and the queries no longer exist.
+    pub fn invalidate(&mut self) {
+        self.free.clear();
+        self.pending.clear();
+        self.current = None;
+        self.in_section = false;
+        self.discard = 0;
+    }
+
+    fn begin_segment(&mut self, label: Option<String>) {
+        let query = match self.free.pop() {
+            Some(query) => query,
+            None => {
+                let mut query = 0;
+                unsafe {
+                    gl_check!(GenQueries(1, &mut query));
+                }
+                query
+            }
+        };
+        unsafe {
+            gl_check!(BeginQuery(gl::TIME_ELAPSED, query));
+        }
+        if let Some(ref mut frame) = self.current {
+            frame.push(Segment { query, label });
+        }
+    }
+
+    fn end_segment(&mut self) {
+        unsafe {
+            gl_check!(EndQuery(gl::TIME_ELAPSED));
+        }
+    }
+
+    // Reads the results of finished frames, oldest first.
+    fn poll(&mut self) {
+        if self.has_disjoint {
+            let mut disjoint: GLint = 0;
+            unsafe {
+                gl_check!(GetIntegerv(GPU_DISJOINT_EXT, &mut disjoint));
+            }
+            if disjoint != 0 {
+                self.discard = self.pending.len();
+            }
+        }
+        while let Some(frame) = self.pending.front() {
+            let last = match frame.last() {
+                Some(segment) => segment.query,
+                None => break,
+            };
+            let mut available: GLint = 0;
+            unsafe {
+                gl_check!(GetQueryObjectiv(
+                    last,
+                    gl::QUERY_RESULT_AVAILABLE,
+                    &mut available
+                ));
+            }
+            if available == 0 {
+                break;
+            }
+
+            let frame = self.pending.pop_front().unwrap();
+            if self.discard > 0 {
+                self.discard -= 1;
+                self.free.extend(frame.iter().map(|segment| segment.query));
+                continue;
+            }
+            let mut timing = GpuTiming::default();
+            for segment in frame {
+                let mut nanos: u64 = 0;
+                unsafe {
+                    gl_check!(GetQueryObjectui64v(
+                        segment.query,
+                        gl::QUERY_RESULT,
+                        &mut nanos
+                    ));
+                }
+                let elapsed = Duration::from_nanos(nanos);
+                timing.frame += elapsed;
+                if let Some(label) = segment.label {
+                    timing.sections.push((label, elapsed));
+                }
+                self.free.push(segment.query);
+            }
+            self.latest = Some(timing);
+        }
+    }
+}
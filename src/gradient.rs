@@ -0,0 +1,355 @@
+//! Linear, radial and conic gradient fills.
+
+// External crates.
+use gl::types::{GLint, GLuint};
+use graphics::math::{self, Matrix2d};
+use graphics::BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+use image::RgbaImage;
+use shader_version::glsl::GLSL;
+use shader_version::Shaders;
+use std::ffi::CString;
+use std::mem;
+
+// Local crate.
+use crate::back_end::USE_WEBGL;
+use crate::debug;
+use crate::error::Error;
+use crate::garbage::{self, Garbage};
+use crate::shader_utils::{compile_shader, link_program, uniform_location, DynamicAttribute};
+use crate::{Texture, TextureSettings};
+
+// The number of chunks to fill up before rendering.
+const CHUNKS: usize = 10;
+
+// The number of texels in a color ramp.
+const RAMP_SIZE: usize = 256;
+
+/// The shape of a gradient, in the coordinates of its transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    /// Changes color along the line from `start` to `end`.
+    Linear {
+        /// The position of the first color stop.
+        start: [f64; 2],
+        /// The position of the last color stop.
+        end: [f64; 2],
+    },
+    /// Changes color with the distance from `center`.
+    Radial {
+        /// The position of the first color stop.
+        center: [f64; 2],
+        /// The distance of the last color stop.
+        radius: f64,
+    },
+    /// Changes color with the angle around `center`.
+    Conic {
+        /// The center of rotation.
+        center: [f64; 2],
+        /// The angle of the first color stop in radians.
+        angle: f64,
+    },
+}
+
+/// How a gradient continues outside of its color stops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spread {
+    /// Uses the color of the nearest end.
+    Pad,
+    /// Starts over from the first color stop.
+    Repeat,
+    /// Goes back and forth between the ends.
+    Reflect,
+}
+
+/// Describes a gradient fill with color stops.
+///
+/// The color stops are stored in a small lookup texture,
+/// which can be restored after the OpenGL context was lost.
+pub struct GradientFill {
+    kind: GradientKind,
+    spread: Spread,
+    ramp: Texture,
+}
+
+impl GradientFill {
+    /// Creates a new gradient fill.
+    ///
+    /// Each color stop is an offset between `0.0` and `1.0` and a color.
+    pub fn new(kind: GradientKind, stops: &[(f32, [f32; 4])], spread: Spread) -> Self {
+        let ramp = RgbaImage::from_raw(RAMP_SIZE as u32, 1, ramp(stops)).unwrap();
+        GradientFill {
+            kind,
+            spread,
+            ramp: Texture::from_image_retained(&ramp, &TextureSettings::new()),
+        }
+    }
+
+    /// Gets the shape of the gradient.
+    pub fn get_kind(&self) -> GradientKind {
+        self.kind
+    }
+
+    /// Gets how the gradient continues outside of its color stops.
+    pub fn get_spread(&self) -> Spread {
+        self.spread
+    }
+
+    /// Re-uploads the color stops after the OpenGL context was restored.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        self.ramp.restore()
+    }
+
+    // Computes the uniform values for drawing with a transform.
+    pub(crate) fn state(&self, transform: Matrix2d) -> GradientState {
+        let (kind, params) = match self.kind {
+            GradientKind::Linear { start, end } => (0, [start[0], start[1], end[0], end[1]]),
+            GradientKind::Radial { center, radius } => (1, [center[0], center[1], radius, 0.0]),
+            GradientKind::Conic { center, angle } => (2, [center[0], center[1], angle, 0.0]),
+        };
+        let spread = match self.spread {
+            Spread::Pad => 0,
+            Spread::Repeat => 1,
+            Spread::Reflect => 2,
+        };
+        let inv = math::invert(transform);
+        GradientState {
+            ramp: self.ramp.get_id(),
+            kind,
+            spread,
+            params: [
+                params[0] as f32,
+                params[1] as f32,
+                params[2] as f32,
+                params[3] as f32,
+            ],
+            // Column major 3x3 matrix.
+            inv_transform: [
+                inv[0][0] as f32,
+                inv[1][0] as f32,
+                0.0,
+                inv[0][1] as f32,
+                inv[1][1] as f32,
+                0.0,
+                inv[0][2] as f32,
+                inv[1][2] as f32,
+                1.0,
+            ],
+        }
+    }
+}
+
+// Samples sorted color stops into a ramp of sRGB colors.
+fn ramp(stops: &[(f32, [f32; 4])]) -> Vec<u8> {
+    let mut stops = stops.to_vec();
+    stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut data = Vec::with_capacity(RAMP_SIZE * 4);
+    for i in 0..RAMP_SIZE {
+        let t = i as f32 / (RAMP_SIZE - 1) as f32;
+        let color = match stops.iter().position(|stop| stop.0 > t) {
+            _ if stops.is_empty() => [0.0; 4],
+            Some(0) => stops[0].1,
+            None => stops[stops.len() - 1].1,
+            Some(j) => {
+                let (a, b) = (stops[j - 1], stops[j]);
+                let s = (t - a.0) / (b.0 - a.0);
+                [
+                    a.1[0] + (b.1[0] - a.1[0]) * s,
+                    a.1[1] + (b.1[1] - a.1[1]) * s,
+                    a.1[2] + (b.1[2] - a.1[2]) * s,
+                    a.1[3] + (b.1[3] - a.1[3]) * s,
+                ]
+            }
+        };
+        for c in &color {
+            data.push((c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+    data
+}
+
+/// The uniform values of a gradient draw call.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct GradientState {
+    ramp: GLuint,
+    kind: GLint,
+    spread: GLint,
+    params: [f32; 4],
+    inv_transform: [f32; 9],
+}
+
+/// Describes how to render gradient filled objects.
+pub(crate) struct Gradient {
+    vertex_shader: GLuint,
+    fragment_shader: GLuint,
+    vertex_source: String,
+    fragment_source: String,
+    pub(crate) program: GLuint,
+    vao: GLuint,
+    inv_transform: GLint,
+    kind: GLint,
+    spread: GLint,
+    params: GLint,
+    pos: DynamicAttribute,
+    pub(crate) pos_buffer: Vec<[f32; 2]>,
+    pub(crate) offset: usize,
+    pub(crate) last_state: Option<GradientState>,
+}
+
+impl Drop for Gradient {
+    fn drop(&mut self) {
+        garbage::delete(Garbage::VertexArray(self.vao));
+        garbage::delete(Garbage::Program(self.program));
+        garbage::delete(Garbage::Shader(self.vertex_shader));
+        garbage::delete(Garbage::Shader(self.fragment_shader));
+    }
+}
+
+impl Gradient {
+    /// Generate using the built-in gradient shaders.
+    ///
+    /// # Panics
+    /// If the built-in shaders fail to compile
+    pub fn new(glsl: GLSL) -> Self {
+        let mut vertex_shaders = Shaders::new();
+        if USE_WEBGL {
+            vertex_shaders
+                .set(
+                    GLSL::V1_20,
+                    include_str!("shaders/gradient/120_webgl.glslv"),
+                )
+                .set(
+                    GLSL::V1_50,
+                    include_str!("shaders/gradient/150_core_webgl.glslv"),
+                )
+        } else {
+            vertex_shaders
+                .set(GLSL::V1_20, include_str!("shaders/gradient/120.glslv"))
+                .set(GLSL::V1_50, include_str!("shaders/gradient/150_core.glslv"))
+        };
+
+        let mut fragment_shaders = Shaders::new();
+        if USE_WEBGL {
+            fragment_shaders
+                .set(
+                    GLSL::V1_20,
+                    include_str!("shaders/gradient/120_webgl.glslf"),
+                )
+                .set(
+                    GLSL::V1_50,
+                    include_str!("shaders/gradient/150_core_webgl.glslf"),
+                )
+        } else {
+            fragment_shaders
+                .set(GLSL::V1_20, include_str!("shaders/gradient/120.glslf"))
+                .set(GLSL::V1_50, include_str!("shaders/gradient/150_core.glslf"))
+        };
+
+        let v_shader = vertex_shaders.get(glsl).unwrap();
+        let f_shader = fragment_shaders.get(glsl).unwrap();
+        Gradient::from_sources(v_shader, f_shader).unwrap()
+    }
+
+    /// Recreates the OpenGL objects after the context was lost.
+    ///
+    /// The old objects belong to the lost context and are not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh = Gradient::from_sources(&self.vertex_source, &self.fragment_source)?;
+        mem::replace(self, fresh).invalidate();
+        Ok(())
+    }
+
+    fn from_sources(v_shader: &str, f_shader: &str) -> Result<Self, Error> {
+        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)?;
+
+        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)?;
+
+        let program;
+        unsafe {
+            program = gl_check!(CreateProgram());
+            gl_check!(AttachShader(program, v_shader_compiled));
+            gl_check!(AttachShader(program, f_shader_compiled));
+
+            let c_o_color = CString::new("o_Color").unwrap();
+            if !USE_WEBGL {
+                gl_check!(BindFragDataLocation(program, 0, c_o_color.as_ptr()));
+            }
+            drop(c_o_color);
+        }
+
+        let mut vao = 0;
+        unsafe {
+            gl_check!(GenVertexArrays(1, &mut vao));
+        }
+        link_program(program)?;
+        let pos = DynamicAttribute::xy(program, "pos", vao)?;
+        let inv_transform = uniform_location(program, "inv_transform")? as GLint;
+        let kind = uniform_location(program, "kind")? as GLint;
+        let spread = uniform_location(program, "spread")? as GLint;
+        let params = uniform_location(program, "params")? as GLint;
+        debug::label(gl::PROGRAM, program, "Gradient");
+        debug::label(gl::VERTEX_ARRAY, vao, "Gradient");
+        pos.label("Gradient pos");
+        Ok(Gradient {
+            vao,
+            vertex_shader: v_shader_compiled,
+            fragment_shader: f_shader_compiled,
+            vertex_source: v_shader.to_string(),
+            fragment_source: f_shader.to_string(),
+            program,
+            inv_transform,
+            kind,
+            spread,
+            params,
+            pos,
+            pos_buffer: vec![[0.0; 2]; CHUNKS * BUFFER_SIZE],
+            offset: 0,
+            last_state: None,
+        })
+    }
+
+    // Forgets the OpenGL objects without deleting them.
+    fn invalidate(&mut self) {
+        self.vao = 0;
+        self.program = 0;
+        self.vertex_shader = 0;
+        self.fragment_shader = 0;
+        self.pos.invalidate();
+    }
+
+    pub(crate) fn flush(&mut self) {
+        if let Some(state) = self.last_state {
+            unsafe {
+                gl_check!(BindVertexArray(self.vao));
+                gl_check!(BindTexture(gl::TEXTURE_2D, state.ramp));
+                gl_check!(UniformMatrix3fv(
+                    self.inv_transform,
+                    1,
+                    gl::FALSE,
+                    state.inv_transform.as_ptr()
+                ));
+                gl_check!(Uniform1i(self.kind, state.kind));
+                gl_check!(Uniform1i(self.spread, state.spread));
+                let p = state.params;
+                gl_check!(Uniform4f(self.params, p[0], p[1], p[2], p[3]));
+                // Render triangles whether they are facing
+                // clockwise or counter clockwise.
+                gl_check!(Disable(gl::CULL_FACE));
+                self.pos.set(&self.pos_buffer[..self.offset]);
+                gl_check!(DrawArrays(gl::TRIANGLES, 0, self.offset as i32));
+                gl_check!(BindVertexArray(0));
+            }
+        }
+
+        self.offset = 0;
+    }
+}
+
+#[test]
+fn test_ramp() {
+    let data = ramp(&[(1.0, [1.0; 4]), (0.0, [0.0, 0.0, 0.0, 1.0])]);
+    assert_eq!(data.len(), RAMP_SIZE * 4);
+    assert_eq!(&data[..4], &[0, 0, 0, 255]);
+    assert_eq!(&data[data.len() - 4..], &[255, 255, 255, 255]);
+    assert_eq!(data[RAMP_SIZE / 2 * 4], 128);
+}
@@ -0,0 +1,157 @@
+//! Reloading textures when their image files change on disk.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::Error;
+use crate::{ImageSize, Texture, TextureLoadOptions, TextureSettings};
+
+/// Identifies a texture in a `TextureRegistry`.
+///
+/// Stays valid when the texture is reloaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(usize);
+
+/// Textures loaded from files, which are reloaded when the files change.
+///
+/// Changes are found by polling modification times,
+/// so call `reload_changed` now and then, for example once a second.
+/// A texture keeping its size is updated in place,
+/// otherwise it gets new storage, keeping its settings and other parameters.
+/// New storage has a new OpenGL id, so look up `get_id` again after a reload
+/// instead of caching it.
+pub struct TextureRegistry {
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    path: PathBuf,
+    options: TextureLoadOptions,
+    modified: Option<SystemTime>,
+    texture: Texture,
+}
+
+impl Default for TextureRegistry {
+    fn default() -> Self {
+        TextureRegistry::new()
+    }
+}
+
+impl TextureRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        TextureRegistry { entries: vec![] }
+    }
+
+    /// Loads a texture with `Texture::from_path` and watches its file.
+    pub fn load<P>(&mut self, path: P, settings: &TextureSettings) -> Result<TextureId, Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.load_with_options(path, settings, &TextureLoadOptions::new())
+    }
+
+    /// Loads a texture with `Texture::from_path_with_options` and watches its file.
+    ///
+    /// The options are applied again on every reload.
+    pub fn load_with_options<P>(
+        &mut self,
+        path: P,
+        settings: &TextureSettings,
+        options: &TextureLoadOptions,
+    ) -> Result<TextureId, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let modified = modified(path);
+        let texture = Texture::from_path_with_options(path, settings, options)?;
+        self.entries.push(Entry {
+            path: path.to_path_buf(),
+            options: *options,
+            modified,
+            texture,
+        });
+        Ok(TextureId(self.entries.len() - 1))
+    }
+
+    /// Gets a texture.
+    pub fn get(&self, id: TextureId) -> &Texture {
+        &self.entries[id.0].texture
+    }
+
+    /// Gets a texture mutably.
+    pub fn get_mut(&mut self, id: TextureId) -> &mut Texture {
+        &mut self.entries[id.0].texture
+    }
+
+    /// Gets the file a texture was loaded from.
+    pub fn get_path(&self, id: TextureId) -> &Path {
+        &self.entries[id.0].path
+    }
+
+    /// Reloads the textures whose files were modified since they were loaded.
+    ///
+    /// Returns the reloaded textures and the errors of files that failed to load,
+    /// for example while an art tool is still writing them.
+    /// Failed textures keep their old contents and are tried again on the next call.
+    pub fn reload_changed(&mut self) -> Vec<(TextureId, Result<(), Error>)> {
+        let mut reloaded = vec![];
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            let modified = match changed(&entry.path, entry.modified) {
+                Some(modified) => modified,
+                None => continue,
+            };
+            let result = entry.reload();
+            if result.is_ok() {
+                entry.modified = Some(modified);
+            }
+            reloaded.push((TextureId(i), result));
+        }
+        reloaded
+    }
+}
+
+impl Entry {
+    fn reload(&mut self) -> Result<(), Error> {
+        let mut img = image::open(&self.path)
+            .map_err(|source| Error::Image {
+                path: Some(self.path.clone()),
+                source,
+            })?
+            .to_rgba8();
+        self.options.apply(&mut img);
+        if img.dimensions() == self.texture.get_size() {
+            self.texture.update(&img);
+            Ok(())
+        } else {
+            self.texture.replace_image(&img)
+        }
+    }
+}
+
+// Gets the modification time of a file if it differs from `last`.
+fn changed(path: &Path, last: Option<SystemTime>) -> Option<SystemTime> {
+    match modified(path) {
+        Some(modified) if Some(modified) != last => Some(modified),
+        _ => None,
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[test]
+fn test_changed() {
+    let path = std::env::temp_dir().join("opengl_graphics_test_changed.png");
+    fs::write(&path, b"image").unwrap();
+    let modified = modified(&path);
+    assert!(modified.is_some());
+    assert_eq!(changed(&path, modified), None);
+    assert_eq!(changed(&path, None), modified);
+    assert_eq!(changed(&path, Some(SystemTime::UNIX_EPOCH)), modified);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(changed(&path, modified), None);
+}
@@ -14,12 +14,27 @@ extern crate shaders_graphics2d as shaders;
 extern crate texture as texture_lib;
 extern crate viewport;
 
+pub use crate::atlas::{AtlasRegion, TextureAtlas};
 pub use crate::back_end::{Colored, GlGraphics, Textured, TexturedColor};
-pub use crate::texture::Texture;
+pub use crate::compressed::CompressedFormat;
+pub use crate::gpu_timer::GpuTiming;
+pub use crate::gradient::{GradientFill, GradientKind, Spread};
+pub use crate::hot_reload::{TextureId, TextureRegistry};
+pub use crate::load_options::TextureLoadOptions;
+pub use crate::nine_slice::{NineSlice, SliceMode};
+pub use crate::sampler::{max_anisotropy, Sampler};
+pub use crate::sdf::SdfShape;
+pub use crate::shared_texture::SharedTexture;
+pub use crate::texture::{PixelFormat, Texture};
+pub use crate::tiled::{max_texture_size, TiledTexture};
+pub use crate::upload::TextureUploader;
+pub use crate::video::{VideoTexture, YuvColorSpace, YuvLayout, YuvRange};
 pub use shader_version::glsl::GLSL;
 pub use shader_version::{OpenGL, Shaders};
 pub use texture_lib::*;
 
+#[macro_use]
+pub mod debug;
 pub mod error;
 pub mod shader_uniforms;
 pub mod shader_utils;
@@ -27,9 +42,24 @@ pub mod shader_utils;
 /// Glyph cache implementation for OpenGL backend.
 pub type GlyphCache<'a> = graphics::glyph_cache::rusttype::GlyphCache<'a, (), Texture>;
 
+mod antialias;
+mod atlas;
 mod back_end;
+mod compressed;
 mod draw_state;
+mod garbage;
+mod gpu_timer;
+mod gradient;
+mod hot_reload;
+mod load_options;
+mod nine_slice;
+mod sampler;
+mod sdf;
+mod shared_texture;
 mod texture;
+mod tiled;
+mod upload;
+mod video;
 
 #[cfg(feature = "glow")]
-pub use gl::set_context;
+pub use gl::{set_context, set_context_lost};
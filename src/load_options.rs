@@ -0,0 +1,144 @@
+//! Pixel transforms applied to images before they are uploaded.
+
+use image::RgbaImage;
+
+/// Transforms applied to the pixels of an image when loading a texture.
+///
+/// They run in this order: color key, channel order, premultiplied alpha
+/// and vertical flip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureLoadOptions {
+    premultiply_alpha: bool,
+    color_key: Option<[u8; 3]>,
+    channels: [usize; 4],
+    flip_vertical: bool,
+}
+
+impl Default for TextureLoadOptions {
+    fn default() -> Self {
+        TextureLoadOptions::new()
+    }
+}
+
+impl TextureLoadOptions {
+    /// Creates options that leave the pixels unchanged.
+    pub fn new() -> TextureLoadOptions {
+        TextureLoadOptions {
+            premultiply_alpha: false,
+            color_key: None,
+            channels: [0, 1, 2, 3],
+            flip_vertical: false,
+        }
+    }
+
+    /// Gets whether the color channels are multiplied by alpha.
+    pub fn get_premultiply_alpha(&self) -> bool {
+        self.premultiply_alpha
+    }
+    /// Sets premultiply alpha.
+    pub fn set_premultiply_alpha(&mut self, val: bool) {
+        self.premultiply_alpha = val;
+    }
+    /// Sets premultiply alpha.
+    pub fn premultiply_alpha(mut self, val: bool) -> Self {
+        self.set_premultiply_alpha(val);
+        self
+    }
+
+    /// Gets the RGB color made fully transparent.
+    pub fn get_color_key(&self) -> Option<[u8; 3]> {
+        self.color_key
+    }
+    /// Sets the color key.
+    pub fn set_color_key(&mut self, val: Option<[u8; 3]>) {
+        self.color_key = val;
+    }
+    /// Sets the color key.
+    pub fn color_key(mut self, val: Option<[u8; 3]>) -> Self {
+        self.set_color_key(val);
+        self
+    }
+
+    /// Gets the source channel of each output channel.
+    ///
+    /// Channels are indexed as `0` red, `1` green, `2` blue and `3` alpha.
+    pub fn get_channels(&self) -> [usize; 4] {
+        self.channels
+    }
+    /// Sets the source channel of each output channel,
+    /// for example `[2, 1, 0, 3]` to swap red and blue.
+    ///
+    /// Panics if an index is larger than `3`.
+    pub fn set_channels(&mut self, val: [usize; 4]) {
+        assert!(val.iter().all(|&c| c < 4), "Channel index out of range");
+        self.channels = val;
+    }
+    /// Sets the source channel of each output channel.
+    pub fn channels(mut self, val: [usize; 4]) -> Self {
+        self.set_channels(val);
+        self
+    }
+
+    /// Gets whether the rows are flipped vertically.
+    pub fn get_flip_vertical(&self) -> bool {
+        self.flip_vertical
+    }
+    /// Sets flip vertical.
+    pub fn set_flip_vertical(&mut self, val: bool) {
+        self.flip_vertical = val;
+    }
+    /// Sets flip vertical.
+    pub fn flip_vertical(mut self, val: bool) -> Self {
+        self.set_flip_vertical(val);
+        self
+    }
+
+    /// Applies the transforms to an image in place.
+    pub fn apply(&self, img: &mut RgbaImage) {
+        if *self == TextureLoadOptions::new() {
+            return;
+        }
+        let swap = self.channels != [0, 1, 2, 3];
+        for pixel in img.pixels_mut() {
+            let p = &mut pixel.0;
+            if self.color_key == Some([p[0], p[1], p[2]]) {
+                p[3] = 0;
+            }
+            if swap {
+                let src = *p;
+                for (dst, &c) in p.iter_mut().zip(self.channels.iter()) {
+                    *dst = src[c];
+                }
+            }
+            if self.premultiply_alpha {
+                let a = p[3] as u32;
+                for c in &mut p[..3] {
+                    *c = ((*c as u32 * a + 127) / 255) as u8;
+                }
+            }
+        }
+        if self.flip_vertical {
+            image::imageops::flip_vertical_in_place(img);
+        }
+    }
+}
+
+#[test]
+fn test_apply() {
+    let mut img = RgbaImage::from_raw(
+        1,
+        2,
+        vec![
+            255, 0, 255, 255, //
+            200, 100, 0, 128,
+        ],
+    )
+    .unwrap();
+    TextureLoadOptions::new()
+        .color_key(Some([255, 0, 255]))
+        .channels([2, 1, 0, 3])
+        .premultiply_alpha(true)
+        .flip_vertical(true)
+        .apply(&mut img);
+    assert_eq!(img.into_raw(), vec![0, 50, 100, 128, 0, 0, 0, 0]);
+}
@@ -0,0 +1,190 @@
+//! Nine-slice scaling of images with fixed corners.
+
+use graphics::math::Matrix2d;
+use graphics::types::{Color, Rectangle, SourceRectangle};
+use graphics::BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+use graphics::{triangulation, DrawState, Graphics, ImageSize};
+
+/// How the edges and center of a nine-slice image fill their area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceMode {
+    /// Scales the slice to fill the area.
+    Stretch,
+    /// Repeats the slice at its original size, cutting off the last copy.
+    Tile,
+}
+
+/// An image split into nine slices, keeping the corners at a fixed size.
+///
+/// All slices are emitted in a single `tri_list_uv` call,
+/// so they are rendered in one batch.
+#[derive(Clone, Copy, Debug)]
+pub struct NineSlice {
+    /// The color
+    pub color: Option<Color>,
+    /// The rectangle to draw the image inside
+    pub rectangle: Rectangle,
+    /// The image source rectangle
+    pub source_rectangle: Option<SourceRectangle>,
+    /// The size of the corners `[left, top, right, bottom]` in source pixels
+    pub insets: [f64; 4],
+    /// How the edges and center fill their area
+    pub mode: SliceMode,
+}
+
+impl NineSlice {
+    /// Creates a new nine-slice image with stretched edges.
+    pub fn new(rectangle: Rectangle, insets: [f64; 4]) -> NineSlice {
+        NineSlice {
+            color: None,
+            rectangle,
+            source_rectangle: None,
+            insets,
+            mode: SliceMode::Stretch,
+        }
+    }
+
+    /// Sets color.
+    pub fn color(mut self, value: Color) -> Self {
+        self.color = Some(value);
+        self
+    }
+
+    /// Sets source rectangle, for example of a texture atlas region.
+    pub fn src_rect(mut self, value: SourceRectangle) -> Self {
+        self.source_rectangle = Some(value);
+        self
+    }
+
+    /// Sets how the edges and center fill their area.
+    pub fn mode(mut self, value: SliceMode) -> Self {
+        self.mode = value;
+        self
+    }
+
+    /// Draws the nine slices.
+    pub fn draw<G>(
+        &self,
+        texture: &<G as Graphics>::Texture,
+        draw_state: &DrawState,
+        transform: Matrix2d,
+        g: &mut G,
+    ) where
+        G: Graphics,
+    {
+        let color = self.color.unwrap_or([1.0; 4]);
+        let source_rectangle = self.source_rectangle.unwrap_or({
+            let (w, h) = texture.get_size();
+            [0.0, 0.0, w as f64, h as f64]
+        });
+        let mut xy = vec![];
+        let mut uv = vec![];
+        for (rect, src_rect) in self.pieces(source_rectangle) {
+            xy.extend_from_slice(&triangulation::rect_tri_list_xy(transform, rect));
+            uv.extend_from_slice(&triangulation::rect_tri_list_uv(texture, src_rect));
+        }
+
+        // Split at triangle borders to stay within the vertex buffer.
+        let chunk = BUFFER_SIZE - BUFFER_SIZE % 3;
+        g.tri_list_uv(draw_state, &color, texture, |f| {
+            for (xy, uv) in xy.chunks(chunk).zip(uv.chunks(chunk)) {
+                f(xy, uv)
+            }
+        });
+    }
+
+    // Computes the destination and source rectangles of all slices.
+    fn pieces(&self, src: SourceRectangle) -> Vec<(Rectangle, SourceRectangle)> {
+        let [left, top, right, bottom] = self.insets;
+        let dst = self.rectangle;
+
+        // Shrink the corners when the rectangle is smaller than them.
+        let mut scale: f64 = 1.0;
+        if left + right > 0.0 {
+            scale = scale.min(dst[2] / (left + right));
+        }
+        if top + bottom > 0.0 {
+            scale = scale.min(dst[3] / (top + bottom));
+        }
+        let scale = scale.max(0.0);
+
+        let cols = spans(
+            [
+                src[0],
+                src[0] + left,
+                src[0] + src[2] - right,
+                src[0] + src[2],
+            ],
+            [
+                dst[0],
+                dst[0] + left * scale,
+                dst[0] + dst[2] - right * scale,
+                dst[0] + dst[2],
+            ],
+            self.mode,
+            scale,
+        );
+        let rows = spans(
+            [
+                src[1],
+                src[1] + top,
+                src[1] + src[3] - bottom,
+                src[1] + src[3],
+            ],
+            [
+                dst[1],
+                dst[1] + top * scale,
+                dst[1] + dst[3] - bottom * scale,
+                dst[1] + dst[3],
+            ],
+            self.mode,
+            scale,
+        );
+
+        let mut pieces = vec![];
+        for &(src_y, dst_y) in &rows {
+            for &(src_x, dst_x) in &cols {
+                pieces.push((
+                    [dst_x[0], dst_y[0], dst_x[1], dst_y[1]],
+                    [src_x[0], src_y[0], src_x[1], src_y[1]],
+                ));
+            }
+        }
+        pieces
+    }
+}
+
+// Splits one axis into `(source, destination)` spans as `[start, size]`.
+fn spans(src: [f64; 4], dst: [f64; 4], mode: SliceMode, scale: f64) -> Vec<([f64; 2], [f64; 2])> {
+    let mut res = vec![];
+    for i in 0..3 {
+        let (src_size, dst_size) = (src[i + 1] - src[i], dst[i + 1] - dst[i]);
+        if src_size <= 0.0 || dst_size <= 0.0 {
+            continue;
+        }
+        let tile = src_size * scale;
+        if i != 1 || mode == SliceMode::Stretch || tile <= 0.0 {
+            res.push(([src[i], src_size], [dst[i], dst_size]));
+            continue;
+        }
+        let mut pos = 0.0;
+        while pos < dst_size {
+            let size = tile.min(dst_size - pos);
+            res.push(([src[i], size / scale], [dst[i] + pos, size]));
+            pos += tile;
+        }
+    }
+    res
+}
+
+#[test]
+fn test_nine_slice_pieces() {
+    let slice = NineSlice::new([0.0, 0.0, 100.0, 50.0], [10.0; 4]);
+    let src = [0.0, 0.0, 30.0, 30.0];
+    assert_eq!(slice.pieces(src).len(), 9);
+
+    // The center is 80x30 with tiles of 10x10, cutting nothing off.
+    let tiled = slice.mode(SliceMode::Tile).pieces(src);
+    assert_eq!(tiled.len(), (1 + 8 + 1) * (1 + 3 + 1));
+    assert!(tiled.contains(&([10.0, 10.0, 10.0, 10.0], [10.0, 10.0, 10.0, 10.0])));
+}
@@ -0,0 +1,157 @@
+//! Sampler objects overriding the filtering of textures per draw call.
+
+use gl::types::{GLenum, GLuint};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::back_end::has_extension;
+use crate::debug;
+use crate::garbage::{self, Garbage};
+use crate::texture::GlSettings;
+use crate::{TextureSettings, Wrap};
+
+// Anisotropic filtering is core in OpenGL 4.6, newer than the bindings.
+pub(crate) const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+// The bits of the largest anisotropy, `0` until queried.
+static MAX_ANISOTROPY: AtomicU32 = AtomicU32::new(0);
+
+/// Gets the largest anisotropy supported by the context.
+///
+/// Returns `1.0` when anisotropic filtering is not supported.
+/// The value is queried once and kept until `GlGraphics::restore`.
+pub fn max_anisotropy() -> f32 {
+    let bits = MAX_ANISOTROPY.load(Ordering::Relaxed);
+    if bits != 0 {
+        return f32::from_bits(bits);
+    }
+    let value = query_max_anisotropy();
+    MAX_ANISOTROPY.store(value.to_bits(), Ordering::Relaxed);
+    value
+}
+
+/// Forgets the largest anisotropy, which is queried again for a new context.
+pub(crate) fn reset_max_anisotropy() {
+    MAX_ANISOTROPY.store(0, Ordering::Relaxed);
+}
+
+/// Returns `true` if setting this anisotropy needs a call to OpenGL.
+///
+/// Skips the call when anisotropic filtering is not supported,
+/// or when it stays disabled.
+pub(crate) fn needs_anisotropy(old: f32, new: f32) -> bool {
+    max_anisotropy() > 1.0 && (old > 1.0 || new > 1.0)
+}
+
+fn query_max_anisotropy() -> f32 {
+    let extensions = [
+        "GL_ARB_texture_filter_anisotropic",
+        "GL_EXT_texture_filter_anisotropic",
+        "EXT_texture_filter_anisotropic",
+    ];
+    if !has_extension(&extensions) {
+        return 1.0;
+    }
+    let mut value = 1.0;
+    unsafe {
+        gl_check!(GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut value));
+    }
+    value.max(1.0)
+}
+
+/// Filtering and wrapping used for textures, independent of their own settings.
+///
+/// Set with `GlGraphics::use_sampler`.
+/// Mipmap filters only work with textures that have mip levels.
+pub struct Sampler {
+    id: GLuint,
+    settings: TextureSettings,
+    anisotropy: f32,
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        garbage::delete(Garbage::Sampler(self.id));
+    }
+}
+
+impl Sampler {
+    /// Creates a sampler with the filters and wrapping of texture settings.
+    pub fn new(settings: &TextureSettings) -> Self {
+        let mut sampler = Sampler {
+            id: 0,
+            settings: *settings,
+            anisotropy: 1.0,
+        };
+        sampler.create();
+        sampler
+    }
+
+    /// Gets the OpenGL id of the sampler.
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Gets the settings of the sampler.
+    pub fn get_settings(&self) -> &TextureSettings {
+        &self.settings
+    }
+
+    /// Sets the anisotropy for sharper textures at steep angles.
+    ///
+    /// Clamped to `1.0..=max_anisotropy()`, where `1.0` disables it.
+    pub fn set_anisotropy(&mut self, anisotropy: f32) {
+        let old = self.anisotropy;
+        self.anisotropy = anisotropy.clamp(1.0, max_anisotropy());
+        if !needs_anisotropy(old, self.anisotropy) {
+            return;
+        }
+        unsafe {
+            gl_check!(SamplerParameterf(
+                self.id,
+                TEXTURE_MAX_ANISOTROPY,
+                self.anisotropy
+            ));
+        }
+    }
+
+    /// Gets the anisotropy.
+    pub fn get_anisotropy(&self) -> f32 {
+        self.anisotropy
+    }
+
+    /// Recreates the sampler after the OpenGL context was restored.
+    ///
+    /// The old sampler belongs to the lost context and is not deleted.
+    pub fn restore(&mut self) {
+        self.create();
+        if self.anisotropy > 1.0 {
+            self.set_anisotropy(self.anisotropy);
+        }
+    }
+
+    fn create(&mut self) {
+        let settings = &self.settings;
+        unsafe {
+            gl_check!(GenSamplers(1, &mut self.id));
+            for &(parameter, value) in &[
+                (gl::TEXTURE_MIN_FILTER, settings.get_gl_min()),
+                (gl::TEXTURE_MAG_FILTER, settings.get_gl_mag()),
+                (gl::TEXTURE_WRAP_S, settings.get_gl_wrap_u()),
+                (gl::TEXTURE_WRAP_T, settings.get_gl_wrap_v()),
+            ] {
+                gl_check!(SamplerParameteri(self.id, parameter, value as i32));
+            }
+            if settings.get_wrap_u() == Wrap::ClampToBorder
+                || settings.get_wrap_v() == Wrap::ClampToBorder
+            {
+                gl_check!(SamplerParameterfv(
+                    self.id,
+                    gl::TEXTURE_BORDER_COLOR,
+                    settings.get_border_color().as_ptr(),
+                ));
+            }
+        }
+        debug::label(gl::SAMPLER, self.id, "Sampler");
+    }
+}
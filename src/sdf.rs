@@ -0,0 +1,312 @@
+//! Shapes rendered from signed distance fields.
+
+// External crates.
+use gl::types::GLuint;
+use graphics::math::{self, Matrix2d};
+use graphics::BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+use shader_version::glsl::GLSL;
+use shader_version::Shaders;
+use std::ffi::CString;
+use std::mem;
+
+// Local crate.
+use crate::back_end::USE_WEBGL;
+use crate::debug;
+use crate::error::Error;
+use crate::garbage::{self, Garbage};
+use crate::shader_utils::{compile_shader, link_program, DynamicAttribute};
+
+// The number of chunks to fill up before rendering.
+const CHUNKS: usize = 10;
+
+/// A shape with edges computed per pixel, which stays smooth at any scale.
+///
+/// Rectangles are `[x, y, width, height]` and corner radii are given
+/// in the order top-left, top-right, bottom-right, bottom-left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SdfShape {
+    /// A filled rectangle with rounded corners.
+    RoundedRectangle {
+        /// The rectangle.
+        rect: [f64; 4],
+        /// The corner radii.
+        radii: [f64; 4],
+    },
+    /// A filled circle.
+    Circle {
+        /// The center.
+        center: [f64; 2],
+        /// The radius.
+        radius: f64,
+    },
+    /// A circle outline.
+    Ring {
+        /// The center.
+        center: [f64; 2],
+        /// The outer radius.
+        radius: f64,
+        /// The width of the ring, inside the radius.
+        width: f64,
+    },
+    /// The outline of a rectangle with rounded corners.
+    Border {
+        /// The outer rectangle.
+        rect: [f64; 4],
+        /// The outer corner radii.
+        radii: [f64; 4],
+        /// The width of the border, inside the rectangle.
+        width: f64,
+    },
+}
+
+impl SdfShape {
+    // Returns the shape as a rounded box with center, half size, radii and border width.
+    fn rounded_box(&self) -> ([f64; 2], [f64; 2], [f64; 4], f64) {
+        let (rect, radii, width) = match *self {
+            SdfShape::RoundedRectangle { rect, radii } => (rect, radii, 0.0),
+            SdfShape::Circle { center, radius } => (circle_rect(center, radius), [radius; 4], 0.0),
+            SdfShape::Ring {
+                center,
+                radius,
+                width,
+            } => (circle_rect(center, radius), [radius; 4], width),
+            SdfShape::Border { rect, radii, width } => (rect, radii, width),
+        };
+        let half = [0.5 * rect[2].abs(), 0.5 * rect[3].abs()];
+        let center = [rect[0] + 0.5 * rect[2], rect[1] + 0.5 * rect[3]];
+        let max_radius = half[0].min(half[1]);
+        let radii = [
+            radii[0].clamp(0.0, max_radius),
+            radii[1].clamp(0.0, max_radius),
+            radii[2].clamp(0.0, max_radius),
+            radii[3].clamp(0.0, max_radius),
+        ];
+        (center, half, radii, width.max(0.0))
+    }
+}
+
+fn circle_rect(center: [f64; 2], radius: f64) -> [f64; 4] {
+    [
+        center[0] - radius,
+        center[1] - radius,
+        2.0 * radius,
+        2.0 * radius,
+    ]
+}
+
+/// The vertices of a quad covering a shape.
+pub(crate) struct SdfQuad {
+    pub pos: [[f32; 2]; 6],
+    pub local: [[f32; 2]; 6],
+    pub radii: [f32; 4],
+    pub params: [f32; 4],
+}
+
+/// Computes a quad covering a shape and its anti-aliased edge.
+///
+/// The `pixel` is the size of a pixel in the coordinates of the shape.
+pub(crate) fn quad(shape: &SdfShape, transform: Matrix2d, pixel: f64) -> SdfQuad {
+    let (center, half, radii, width) = shape.rounded_box();
+    let x = half[0] + pixel;
+    let y = half[1] + pixel;
+    let corners = [[-x, -y], [x, -y], [x, y], [-x, -y], [x, y], [-x, y]];
+    let mut pos = [[0.0; 2]; 6];
+    let mut local = [[0.0; 2]; 6];
+    for (i, corner) in corners.iter().enumerate() {
+        let p = math::transform_pos(transform, [center[0] + corner[0], center[1] + corner[1]]);
+        pos[i] = [p[0] as f32, p[1] as f32];
+        local[i] = [corner[0] as f32, corner[1] as f32];
+    }
+    SdfQuad {
+        pos,
+        local,
+        radii: [
+            radii[0] as f32,
+            radii[1] as f32,
+            radii[2] as f32,
+            radii[3] as f32,
+        ],
+        params: [half[0] as f32, half[1] as f32, width as f32, pixel as f32],
+    }
+}
+
+/// Describes how to render shapes from signed distance fields.
+pub(crate) struct Sdf {
+    vertex_shader: GLuint,
+    fragment_shader: GLuint,
+    vertex_source: String,
+    fragment_source: String,
+    pub(crate) program: GLuint,
+    vao: GLuint,
+    pos: DynamicAttribute,
+    local: DynamicAttribute,
+    color: DynamicAttribute,
+    radii: DynamicAttribute,
+    params: DynamicAttribute,
+    pub(crate) pos_buffer: Vec<[f32; 2]>,
+    pub(crate) local_buffer: Vec<[f32; 2]>,
+    pub(crate) color_buffer: Vec<[f32; 4]>,
+    pub(crate) radii_buffer: Vec<[f32; 4]>,
+    pub(crate) params_buffer: Vec<[f32; 4]>,
+    pub(crate) offset: usize,
+}
+
+impl Drop for Sdf {
+    fn drop(&mut self) {
+        garbage::delete(Garbage::VertexArray(self.vao));
+        garbage::delete(Garbage::Program(self.program));
+        garbage::delete(Garbage::Shader(self.vertex_shader));
+        garbage::delete(Garbage::Shader(self.fragment_shader));
+    }
+}
+
+impl Sdf {
+    /// Generate using the built-in signed distance field shaders.
+    ///
+    /// # Panics
+    /// If the built-in shaders fail to compile
+    pub fn new(glsl: GLSL) -> Self {
+        let mut vertex_shaders = Shaders::new();
+        if USE_WEBGL {
+            vertex_shaders
+                .set(GLSL::V1_20, include_str!("shaders/sdf/120_webgl.glslv"))
+                .set(
+                    GLSL::V1_50,
+                    include_str!("shaders/sdf/150_core_webgl.glslv"),
+                )
+        } else {
+            vertex_shaders
+                .set(GLSL::V1_20, include_str!("shaders/sdf/120.glslv"))
+                .set(GLSL::V1_50, include_str!("shaders/sdf/150_core.glslv"))
+        };
+
+        let mut fragment_shaders = Shaders::new();
+        if USE_WEBGL {
+            fragment_shaders
+                .set(GLSL::V1_20, include_str!("shaders/sdf/120_webgl.glslf"))
+                .set(
+                    GLSL::V1_50,
+                    include_str!("shaders/sdf/150_core_webgl.glslf"),
+                )
+        } else {
+            fragment_shaders
+                .set(GLSL::V1_20, include_str!("shaders/sdf/120.glslf"))
+                .set(GLSL::V1_50, include_str!("shaders/sdf/150_core.glslf"))
+        };
+
+        let v_shader = vertex_shaders.get(glsl).unwrap();
+        let f_shader = fragment_shaders.get(glsl).unwrap();
+        Sdf::from_sources(v_shader, f_shader).unwrap()
+    }
+
+    /// Recreates the OpenGL objects after the context was lost.
+    ///
+    /// The old objects belong to the lost context and are not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh = Sdf::from_sources(&self.vertex_source, &self.fragment_source)?;
+        mem::replace(self, fresh).invalidate();
+        Ok(())
+    }
+
+    fn from_sources(v_shader: &str, f_shader: &str) -> Result<Self, Error> {
+        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)?;
+
+        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)?;
+
+        let program;
+        unsafe {
+            program = gl_check!(CreateProgram());
+            gl_check!(AttachShader(program, v_shader_compiled));
+            gl_check!(AttachShader(program, f_shader_compiled));
+
+            let c_o_color = CString::new("o_Color").unwrap();
+            if !USE_WEBGL {
+                gl_check!(BindFragDataLocation(program, 0, c_o_color.as_ptr()));
+            }
+            drop(c_o_color);
+        }
+
+        let mut vao = 0;
+        unsafe {
+            gl_check!(GenVertexArrays(1, &mut vao));
+        }
+        link_program(program)?;
+        let pos = DynamicAttribute::xy(program, "pos", vao)?;
+        let local = DynamicAttribute::xy(program, "local", vao)?;
+        let color = DynamicAttribute::rgba(program, "color", vao)?;
+        let radii = DynamicAttribute::xyzw(program, "radii", vao)?;
+        let params = DynamicAttribute::xyzw(program, "params", vao)?;
+        debug::label(gl::PROGRAM, program, "Sdf");
+        debug::label(gl::VERTEX_ARRAY, vao, "Sdf");
+        pos.label("Sdf pos");
+        local.label("Sdf local");
+        color.label("Sdf color");
+        radii.label("Sdf radii");
+        params.label("Sdf params");
+        Ok(Sdf {
+            vao,
+            vertex_shader: v_shader_compiled,
+            fragment_shader: f_shader_compiled,
+            vertex_source: v_shader.to_string(),
+            fragment_source: f_shader.to_string(),
+            program,
+            pos,
+            local,
+            color,
+            radii,
+            params,
+            pos_buffer: vec![[0.0; 2]; CHUNKS * BUFFER_SIZE],
+            local_buffer: vec![[0.0; 2]; CHUNKS * BUFFER_SIZE],
+            color_buffer: vec![[0.0; 4]; CHUNKS * BUFFER_SIZE],
+            radii_buffer: vec![[0.0; 4]; CHUNKS * BUFFER_SIZE],
+            params_buffer: vec![[0.0; 4]; CHUNKS * BUFFER_SIZE],
+            offset: 0,
+        })
+    }
+
+    // Forgets the OpenGL objects without deleting them.
+    fn invalidate(&mut self) {
+        self.vao = 0;
+        self.program = 0;
+        self.vertex_shader = 0;
+        self.fragment_shader = 0;
+        self.pos.invalidate();
+        self.local.invalidate();
+        self.color.invalidate();
+        self.radii.invalidate();
+        self.params.invalidate();
+    }
+
+    pub(crate) fn flush(&mut self) {
+        unsafe {
+            gl_check!(BindVertexArray(self.vao));
+            // Render triangles whether they are facing
+            // clockwise or counter clockwise.
+            gl_check!(Disable(gl::CULL_FACE));
+            self.pos.set(&self.pos_buffer[..self.offset]);
+            self.local.set(&self.local_buffer[..self.offset]);
+            self.color.set(&self.color_buffer[..self.offset]);
+            self.radii.set(&self.radii_buffer[..self.offset]);
+            self.params.set(&self.params_buffer[..self.offset]);
+            gl_check!(DrawArrays(gl::TRIANGLES, 0, self.offset as i32));
+            gl_check!(BindVertexArray(0));
+        }
+
+        self.offset = 0;
+    }
+}
+
+#[test]
+fn test_ring_quad() {
+    let ring = SdfShape::Ring {
+        center: [10.0, 20.0],
+        radius: 5.0,
+        width: 2.0,
+    };
+    let quad = quad(&ring, math::identity(), 1.0);
+    assert_eq!(quad.pos[0], [4.0, 14.0]);
+    assert_eq!(quad.pos[2], [16.0, 26.0]);
+    assert_eq!(quad.local[0], [-6.0, -6.0]);
+    assert_eq!(quad.radii, [5.0; 4]);
+    assert_eq!(quad.params, [5.0, 5.0, 2.0, 1.0]);
+}
@@ -6,6 +6,11 @@ use gl::types::{GLboolean, GLenum, GLint, GLsizeiptr, GLuint};
 use std::ffi::CString;
 use std::{mem, ptr};
 
+// Local crate.
+use crate::debug;
+use crate::error::{Error, ShaderStage};
+use crate::garbage::{self, Garbage};
+
 #[cfg(not(feature = "glow"))]
 use gl::types::GLchar;
 
@@ -25,9 +30,7 @@ pub struct DynamicAttribute {
 
 impl Drop for DynamicAttribute {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, &self.vbo);
-        }
+        garbage::delete(Garbage::Buffer(self.vbo));
     }
 }
 
@@ -38,16 +41,16 @@ impl DynamicAttribute {
     fn bind_vao(&self, vao: GLuint) {
         let stride = 0;
         unsafe {
-            gl::BindVertexArray(vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            gl::VertexAttribPointer(
+            gl_check!(BindVertexArray(vao));
+            gl_check!(BindBuffer(gl::ARRAY_BUFFER, self.vbo));
+            gl_check!(VertexAttribPointer(
                 self.location,
                 self.size,
                 self.ty,
                 self.normalize,
                 stride,
                 ptr::null(),
-            );
+            ));
         }
     }
 
@@ -58,11 +61,11 @@ impl DynamicAttribute {
         normalize: GLboolean,
         ty: GLenum,
         vao: GLuint,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, Error> {
         let location = attribute_location(program, name)?;
         let mut vbo = 0;
         unsafe {
-            gl::GenBuffers(1, &mut vbo);
+            gl_check!(GenBuffers(1, &mut vbo));
         }
         let res = DynamicAttribute {
             vbo,
@@ -76,65 +79,91 @@ impl DynamicAttribute {
     }
 
     /// Create XYZ vertex attribute.
-    pub fn xyz(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, String> {
+    pub fn xyz(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, Error> {
         DynamicAttribute::new(program, name, 3, gl::FALSE, gl::FLOAT, vao)
     }
 
     /// Create XY vertex attribute.
-    pub fn xy(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, String> {
+    pub fn xy(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, Error> {
         DynamicAttribute::new(program, name, 2, gl::FALSE, gl::FLOAT, vao)
     }
 
+    /// Create XYZW vertex attribute.
+    pub fn xyzw(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, Error> {
+        DynamicAttribute::new(program, name, 4, gl::FALSE, gl::FLOAT, vao)
+    }
+
     /// Create RGB color attribute.
-    pub fn rgb(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, String> {
+    pub fn rgb(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, Error> {
         DynamicAttribute::new(program, name, 3, gl::FALSE, gl::FLOAT, vao)
     }
 
     /// Create RGBA color attribute.
-    pub fn rgba(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, String> {
+    pub fn rgba(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, Error> {
         DynamicAttribute::new(program, name, 4, gl::FALSE, gl::FLOAT, vao)
     }
 
     /// Create texture coordinate attribute.
-    pub fn uv(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, String> {
+    pub fn uv(program: GLuint, name: &str, vao: GLuint) -> Result<DynamicAttribute, Error> {
         DynamicAttribute::new(program, name, 2, gl::FALSE, gl::FLOAT, vao)
     }
 
+    /// Labels the vertex buffer object for debugging tools.
+    pub(crate) fn label(&self, label: &str) {
+        debug::label(gl::BUFFER, self.vbo, label);
+    }
+
+    /// Forgets the vertex buffer object without deleting it.
+    ///
+    /// Used when the OpenGL context was lost and the buffer no longer exists.
+    pub(crate) fn invalidate(&mut self) {
+        self.vbo = 0;
+    }
+
     /// Sets attribute data.
     pub unsafe fn set<T>(&self, data: &[T]) {
-        gl::EnableVertexAttribArray(self.location);
-        gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-        gl::BufferData(
+        gl_check!(EnableVertexAttribArray(self.location));
+        gl_check!(BindBuffer(gl::ARRAY_BUFFER, self.vbo));
+        gl_check!(BufferData(
             gl::ARRAY_BUFFER,
             data.len() as GLsizeiptr * mem::size_of::<T>() as GLsizeiptr,
             mem::transmute(data.as_ptr()),
             gl::DYNAMIC_DRAW,
-        );
+        ));
     }
 }
 
 /// Compiles a shader.
 ///
-/// Returns a shader or a message with the error.
-pub fn compile_shader(shader_type: GLenum, source: &str) -> Result<GLuint, String> {
+/// Returns a shader or an error with the log of the compiler.
+pub fn compile_shader(shader_type: GLenum, source: &str) -> Result<GLuint, Error> {
+    let stage = ShaderStage::from_gl(shader_type);
+    if !gl::CreateShader::is_loaded() {
+        return Err(Error::ContextMissing);
+    }
     unsafe {
-        let shader = gl::CreateShader(shader_type);
         let c_source = match CString::new(source) {
             Ok(x) => x,
-            Err(err) => return Err(format!("compile_shader: {}", err)),
+            Err(err) => {
+                return Err(Error::ShaderCompile {
+                    stage,
+                    log: err.to_string(),
+                })
+            }
         };
-        gl::ShaderSource(shader, 1, &c_source.as_ptr(), ptr::null());
+        let shader = gl_check!(CreateShader(shader_type));
+        gl_check!(ShaderSource(shader, 1, &c_source.as_ptr(), ptr::null()));
         drop(c_source);
-        gl::CompileShader(shader);
+        gl_check!(CompileShader(shader));
         let mut status = gl::FALSE as GLint;
 
         #[cfg(feature = "glow")]
         {
-            gl::GetCompleStatus(shader, &mut status);
+            gl_check!(GetCompleStatus(shader, &mut status));
         }
         #[cfg(not(feature = "glow"))]
         {
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+            gl_check!(GetShaderiv(shader, gl::COMPILE_STATUS, &mut status));
         }
 
         if status == (gl::TRUE as GLint) {
@@ -142,50 +171,92 @@ pub fn compile_shader(shader_type: GLenum, source: &str) -> Result<GLuint, Strin
         } else {
             #[cfg(feature = "glow")]
             {
-                Err(gl::GetShaderInfoLog(shader))
+                let log = gl_check!(GetShaderInfoLog(shader));
+                gl_check!(DeleteShader(shader));
+                Err(Error::ShaderCompile { stage, log })
             }
             #[cfg(not(feature = "glow"))]
             {
                 let mut len = 0;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+                gl_check!(GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len));
 
                 if len == 0 {
-                    Err("Compilation failed with no log. \
-                       The OpenGL context might have been created on another thread, \
-                       or not have been created."
-                        .to_string())
+                    Err(Error::ShaderCompile {
+                        stage,
+                        log: "Compilation failed with no log. \
+                           The OpenGL context might have been created on another thread, \
+                           or not have been created."
+                            .to_string(),
+                    })
                 } else {
                     // Subtract 1 to skip the trailing null character.
                     let mut buf = vec![0; len as usize - 1];
-                    gl::GetShaderInfoLog(
+                    gl_check!(GetShaderInfoLog(
                         shader,
                         len,
                         ptr::null_mut(),
                         buf.as_mut_ptr() as *mut GLchar,
-                    );
+                    ));
 
-                    gl::DeleteShader(shader);
+                    gl_check!(DeleteShader(shader));
 
-                    Err(String::from_utf8(buf).expect("ShaderInfoLog not valid utf8"))
+                    let log = String::from_utf8_lossy(&buf).into_owned();
+                    Err(Error::ShaderCompile { stage, log })
                 }
             }
         }
     }
 }
 
+/// Links a program from its attached shaders.
+///
+/// Returns an error with the log of the linker.
+pub fn link_program(program: GLuint) -> Result<(), Error> {
+    unsafe {
+        gl_check!(LinkProgram(program));
+        let mut status = gl::FALSE as GLint;
+        gl_check!(GetProgramiv(program, gl::LINK_STATUS, &mut status));
+        if status == (gl::TRUE as GLint) {
+            return Ok(());
+        }
+
+        #[cfg(feature = "glow")]
+        {
+            let log = gl_check!(GetProgramInfoLog(program));
+            Err(Error::ProgramLink { log })
+        }
+        #[cfg(not(feature = "glow"))]
+        {
+            let mut len = 0;
+            gl_check!(GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len));
+            let mut buf = vec![0; (len as usize).saturating_sub(1)];
+            if len > 0 {
+                gl_check!(GetProgramInfoLog(
+                    program,
+                    len,
+                    ptr::null_mut(),
+                    buf.as_mut_ptr() as *mut GLchar,
+                ));
+            }
+            let log = String::from_utf8_lossy(&buf).into_owned();
+            Err(Error::ProgramLink { log })
+        }
+    }
+}
+
 /// Finds attribute location from a program.
 ///
 /// Returns `Err` if there is no attribute with such name.
-pub fn attribute_location(program: GLuint, name: &str) -> Result<GLuint, String> {
+pub fn attribute_location(program: GLuint, name: &str) -> Result<GLuint, Error> {
     unsafe {
         let c_name = match CString::new(name) {
             Ok(x) => x,
-            Err(err) => return Err(format!("attribute_location: {}", err)),
+            Err(_) => return Err(Error::MissingAttribute(name.into())),
         };
-        let id = gl::GetAttribLocation(program, c_name.as_ptr());
+        let id = gl_check!(GetAttribLocation(program, c_name.as_ptr()));
         drop(c_name);
         if id < 0 {
-            Err(format!("Attribute '{}' does not exists in shader", name))
+            Err(Error::MissingAttribute(name.into()))
         } else {
             Ok(id as GLuint)
         }
@@ -195,16 +266,16 @@ pub fn attribute_location(program: GLuint, name: &str) -> Result<GLuint, String>
 /// Finds uniform location from a program.
 ///
 /// Returns `Err` if there is no uniform with such name.
-pub fn uniform_location(program: GLuint, name: &str) -> Result<GLuint, String> {
+pub fn uniform_location(program: GLuint, name: &str) -> Result<GLuint, Error> {
     unsafe {
         let c_name = match CString::new(name) {
             Ok(x) => x,
-            Err(err) => return Err(format!("uniform_location: {}", err)),
+            Err(_) => return Err(Error::MissingUniform(name.into())),
         };
-        let id = gl::GetUniformLocation(program, c_name.as_ptr());
+        let id = gl_check!(GetUniformLocation(program, c_name.as_ptr()));
         drop(c_name);
         if id < 0 {
-            Err(format!("Uniform '{}' does not exists in shader", name))
+            Err(Error::MissingUniform(name.into()))
         } else {
             Ok(id as GLuint)
         }
@@ -0,0 +1,66 @@
+//! Reference counted textures shared between many owners.
+
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::{ImageSize, Texture};
+
+/// A cheaply clonable handle to a texture.
+///
+/// Derefs to `Texture`, so `&*handle` can be drawn with `graphics::Image`.
+/// The OpenGL texture is deleted when the last handle is dropped.
+#[derive(Clone)]
+pub struct SharedTexture(Rc<Texture>);
+
+impl SharedTexture {
+    /// Shares a texture.
+    pub fn new(texture: Texture) -> Self {
+        SharedTexture(Rc::new(texture))
+    }
+
+    /// Gets the number of handles to the texture.
+    pub fn handle_count(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+
+    /// Returns `true` if both handles share the same texture.
+    pub fn ptr_eq(&self, other: &SharedTexture) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Gets the texture mutably, if this is the only handle.
+    pub fn get_mut(&mut self) -> Option<&mut Texture> {
+        Rc::get_mut(&mut self.0)
+    }
+
+    /// Takes the texture back, if this is the only handle.
+    pub fn try_unwrap(self) -> Result<Texture, Self> {
+        Rc::try_unwrap(self.0).map_err(SharedTexture)
+    }
+}
+
+impl From<Texture> for SharedTexture {
+    fn from(texture: Texture) -> Self {
+        SharedTexture::new(texture)
+    }
+}
+
+impl Deref for SharedTexture {
+    type Target = Texture;
+
+    fn deref(&self) -> &Texture {
+        &self.0
+    }
+}
+
+impl AsRef<Texture> for SharedTexture {
+    fn as_ref(&self) -> &Texture {
+        &self.0
+    }
+}
+
+impl ImageSize for SharedTexture {
+    fn get_size(&self) -> (u32, u32) {
+        self.0.get_size()
+    }
+}
@@ -1,13 +1,23 @@
 use gl::types::GLuint;
 use image::{self, DynamicImage, RgbaImage};
 
+use std::mem;
 use std::path::Path;
+use std::ptr;
 
+use crate::back_end::USE_WEBGL;
+use crate::compressed::{self, CompressedFormat};
+use crate::debug;
+use crate::error::Error;
+use crate::garbage::{self, Garbage};
+use crate::load_options::TextureLoadOptions;
+use crate::sampler::{max_anisotropy, needs_anisotropy, TEXTURE_MAX_ANISOTROPY};
+use crate::tiled::max_texture_size;
 use crate::{
-    ops, CreateTexture, Filter, Format, ImageSize, TextureOp, TextureSettings, UpdateTexture, Wrap,
+    CreateTexture, Filter, Format, ImageSize, TextureOp, TextureSettings, UpdateTexture, Wrap,
 };
 
-trait GlSettings {
+pub(crate) trait GlSettings {
     fn get_gl_mag(&self) -> gl::types::GLenum;
     fn get_gl_min(&self) -> gl::types::GLenum;
     #[allow(dead_code)]
@@ -75,8 +85,140 @@ impl GlSettings for TextureSettings {
     }
 }
 
+/// The layout of pixel data stored in a texture.
+///
+/// Covers more layouts than `Format`, which only knows about RGBA.
+/// On WebGL, formats relying on swizzles or BGRA order
+/// are converted to RGBA when uploading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// `(red)` with values 0-255, sampled as `(red, 0, 0, 1)`.
+    R8,
+    /// `(alpha)` with values 0-255, sampled as `(1, 1, 1, alpha)`.
+    ///
+    /// Useful for glyphs and other masks.
+    A8,
+    /// `(luminance)` with values 0-255, sampled as `(luminance, luminance, luminance, 1)`.
+    L8,
+    /// `(luminance, alpha)` with values 0-255.
+    La8,
+    /// `(red, green)` with values 0-255.
+    Rg8,
+    /// `(red, green, blue)` with values 0-255.
+    Rgb8,
+    /// `(red, green, blue, alpha)` with values 0-255.
+    Rgba8,
+    /// `(blue, green, red, alpha)` with values 0-255.
+    Bgra8,
+    /// `(red, green, blue, alpha)` with 16 bit unsigned values in native byte order.
+    ///
+    /// Not available on WebGL.
+    Rgba16,
+    /// `(red, green, blue, alpha)` with 16 bit floats in native byte order.
+    Rgba16f,
+    /// `(red)` with 32 bit floats in native byte order.
+    R32f,
+    /// `(red, green, blue, alpha)` with 32 bit floats in native byte order.
+    Rgba32f,
+}
+
+impl From<Format> for PixelFormat {
+    fn from(format: Format) -> PixelFormat {
+        match format {
+            Format::Rgba8 => PixelFormat::Rgba8,
+        }
+    }
+}
+
+impl PixelFormat {
+    /// Gets the number of bytes per pixel.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match *self {
+            PixelFormat::R8 | PixelFormat::A8 | PixelFormat::L8 => 1,
+            PixelFormat::La8 | PixelFormat::Rg8 => 2,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 | PixelFormat::R32f => 4,
+            PixelFormat::Rgba16 | PixelFormat::Rgba16f => 8,
+            PixelFormat::Rgba32f => 16,
+        }
+    }
+
+    // Whether the format is uploaded as RGBA on WebGL.
+    fn is_expanded(&self) -> bool {
+        USE_WEBGL
+            && matches!(
+                *self,
+                PixelFormat::A8 | PixelFormat::L8 | PixelFormat::La8 | PixelFormat::Bgra8
+            )
+    }
+}
+
+// How pixels of a format are passed to OpenGL.
+struct GlFormat {
+    internal_format: gl::types::GLenum,
+    format: gl::types::GLenum,
+    ty: gl::types::GLenum,
+    swizzle: Option<[gl::types::GLenum; 4]>,
+}
+
+// Gets the OpenGL parameters to upload pixels of a format.
+//
+// Only 8 bit color formats are stored as sRGB, when gamma is not converted in shaders.
+fn gl_format(format: PixelFormat, settings: &TextureSettings) -> GlFormat {
+    let srgb = !settings.get_convert_gamma();
+    let format = if format.is_expanded() {
+        PixelFormat::Rgba8
+    } else {
+        format
+    };
+    let (internal_format, gl_format, ty) = match format {
+        PixelFormat::R8 | PixelFormat::A8 | PixelFormat::L8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+        PixelFormat::La8 | PixelFormat::Rg8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgb8 if srgb => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgb8 => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgba8 if srgb => (gl::SRGB_ALPHA, gl::RGBA, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgba8 => (gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE),
+        PixelFormat::Bgra8 if srgb => (gl::SRGB_ALPHA, gl::BGRA, gl::UNSIGNED_BYTE),
+        PixelFormat::Bgra8 => (gl::RGBA, gl::BGRA, gl::UNSIGNED_BYTE),
+        PixelFormat::Rgba16 => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+        PixelFormat::Rgba16f => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        PixelFormat::R32f => (gl::R32F, gl::RED, gl::FLOAT),
+        PixelFormat::Rgba32f => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+    };
+    let swizzle = match format {
+        PixelFormat::A8 => Some([gl::ONE, gl::ONE, gl::ONE, gl::RED]),
+        PixelFormat::L8 => Some([gl::RED, gl::RED, gl::RED, gl::ONE]),
+        PixelFormat::La8 => Some([gl::RED, gl::RED, gl::RED, gl::GREEN]),
+        _ => None,
+    };
+    GlFormat {
+        internal_format,
+        format: gl_format,
+        ty,
+        swizzle,
+    }
+}
+
+// Converts 8 bit formats without WebGL support to RGBA.
+fn expand_rgba8(format: PixelFormat, memory: &[u8]) -> Vec<u8> {
+    match format {
+        PixelFormat::A8 => memory.iter().flat_map(|&a| [255, 255, 255, a]).collect(),
+        PixelFormat::L8 => memory.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        PixelFormat::La8 => memory
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        PixelFormat::Bgra8 => memory
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect(),
+        _ => memory.to_vec(),
+    }
+}
+
 /// Wraps OpenGL texture data.
-/// The texture gets deleted when running out of scope.
+/// The texture gets deleted when running out of scope,
+/// unless it was created with `borrowed`.
 ///
 /// In order to create a texture the function `GenTextures` must be loaded.
 /// This is done automatically by the window back-ends in Piston.
@@ -84,13 +226,62 @@ pub struct Texture {
     id: GLuint,
     width: u32,
     height: u32,
+    format: PixelFormat,
+    compressed: Option<CompressedFormat>,
+    // Whether mipmaps are generated again after updates.
+    regenerate_mipmap: bool,
+    lod_bias: f32,
+    max_level: Option<u32>,
+    anisotropy: f32,
+    settings: TextureSettings,
+    retained: Option<Retained>,
+    label: Option<String>,
+    // Whether the texture is deleted on drop.
+    owned: bool,
+}
+
+// A CPU copy of the texture data, used to re-upload after context loss.
+struct Retained {
+    memory: Vec<u8>,
 }
 
 impl Texture {
-    /// Creates a new texture.
+    /// Creates a new texture, taking ownership of the OpenGL id.
     #[inline(always)]
     pub fn new(id: GLuint, width: u32, height: u32) -> Self {
-        Texture { id, width, height }
+        Texture {
+            id,
+            width,
+            height,
+            format: PixelFormat::Rgba8,
+            compressed: None,
+            regenerate_mipmap: false,
+            lod_bias: 0.0,
+            max_level: None,
+            anisotropy: 1.0,
+            settings: TextureSettings::new(),
+            retained: None,
+            label: None,
+            owned: true,
+        }
+    }
+
+    /// Wraps a texture owned by someone else, which is not deleted on drop.
+    ///
+    /// Use this for textures of a video decoder, another renderer or a shared context.
+    /// The owner must keep the texture alive while it is drawn,
+    /// until the next `GlGraphics::draw_end`.
+    #[inline(always)]
+    pub fn borrowed(id: GLuint, width: u32, height: u32) -> Self {
+        let mut texture = Texture::new(id, width, height);
+        texture.owned = false;
+        texture
+    }
+
+    /// Returns `true` if the texture is not deleted on drop, see `borrowed`.
+    #[inline(always)]
+    pub fn is_borrowed(&self) -> bool {
+        !self.owned
     }
 
     /// Gets the OpenGL id of the texture.
@@ -99,70 +290,638 @@ impl Texture {
         self.id
     }
 
+    /// Gets the layout of the pixel data.
+    #[inline(always)]
+    pub fn get_format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Gets the block compressed format, if the texture was loaded with `from_compressed`.
+    #[inline(always)]
+    pub fn get_compressed_format(&self) -> Option<CompressedFormat> {
+        self.compressed
+    }
+
+    /// Gets the settings the texture is sampled with.
+    ///
+    /// Textures created with `new` report the default settings.
+    #[inline(always)]
+    pub fn get_settings(&self) -> &TextureSettings {
+        &self.settings
+    }
+
+    /// Changes the filters, wrapping, border color and mipmaps without re-uploading.
+    ///
+    /// Mip levels are generated when `generate_mipmap` is newly enabled.
+    /// The gamma conversion and compression are part of the stored data,
+    /// so they keep the values the texture was created with.
+    /// Compressed textures can not generate levels,
+    /// so they keep whether they are sampled with mipmaps.
+    pub fn set_settings(&mut self, settings: &TextureSettings) {
+        let settings = changed_settings(&self.settings, settings, self.compressed.is_some());
+        unsafe {
+            gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+            set_parameters(&settings);
+        }
+        if settings.get_generate_mipmap() && !self.settings.get_generate_mipmap() {
+            self.generate_mipmap();
+            self.regenerate_mipmap = true;
+        } else if !settings.get_generate_mipmap() {
+            self.regenerate_mipmap = false;
+        }
+        self.settings = settings;
+    }
+
+    /// Generates the mip levels from the full size image.
+    ///
+    /// Does nothing for compressed textures.
+    pub fn generate_mipmap(&mut self) {
+        if self.compressed.is_some() {
+            return;
+        }
+        unsafe {
+            gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+            gl_check!(GenerateMipmap(gl::TEXTURE_2D));
+        }
+    }
+
+    /// Sets whether mip levels are generated again after each update.
+    ///
+    /// Enabled for textures created with `generate_mipmap` in their settings.
+    /// When updating many regions, disable this and call `generate_mipmap` once.
+    pub fn set_regenerate_mipmap(&mut self, value: bool) {
+        self.regenerate_mipmap = value;
+    }
+
+    /// Gets whether mip levels are generated again after each update.
+    pub fn get_regenerate_mipmap(&self) -> bool {
+        self.regenerate_mipmap
+    }
+
+    /// Sets the bias added to the mip level selected for sampling.
+    ///
+    /// Positive values select smaller levels, giving a blurrier image.
+    /// Ignored on WebGL, which has no LOD bias.
+    pub fn set_lod_bias(&mut self, bias: f32) {
+        self.lod_bias = bias;
+        if USE_WEBGL {
+            return;
+        }
+        unsafe {
+            gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+            gl_check!(TexParameterf(gl::TEXTURE_2D, gl::TEXTURE_LOD_BIAS, bias));
+        }
+    }
+
+    /// Gets the bias added to the mip level selected for sampling.
+    pub fn get_lod_bias(&self) -> f32 {
+        self.lod_bias
+    }
+
+    /// Sets the smallest mip level used for sampling, `None` for all levels.
+    pub fn set_max_level(&mut self, level: Option<u32>) {
+        self.max_level = level;
+        // OpenGL defaults to 1000 levels.
+        let value = level.unwrap_or(1000) as i32;
+        unsafe {
+            gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+            gl_check!(TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, value));
+        }
+    }
+
+    /// Gets the smallest mip level used for sampling.
+    pub fn get_max_level(&self) -> Option<u32> {
+        self.max_level
+    }
+
+    /// Sets the anisotropy for sharper textures at steep angles.
+    ///
+    /// Clamped to `1.0..=max_anisotropy()`, where `1.0` disables it.
+    pub fn set_anisotropy(&mut self, anisotropy: f32) {
+        let old = self.anisotropy;
+        self.anisotropy = anisotropy.clamp(1.0, max_anisotropy());
+        if !needs_anisotropy(old, self.anisotropy) {
+            return;
+        }
+        unsafe {
+            gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+            gl_check!(TexParameterf(
+                gl::TEXTURE_2D,
+                TEXTURE_MAX_ANISOTROPY,
+                self.anisotropy
+            ));
+        }
+    }
+
+    /// Gets the anisotropy.
+    pub fn get_anisotropy(&self) -> f32 {
+        self.anisotropy
+    }
+
+    /// Labels the texture for debugging tools.
+    ///
+    /// Does nothing unless object labels are enabled, see `debug::set_object_labels`.
+    /// The label is kept when the texture is restored or replaced.
+    pub fn set_label(&mut self, label: &str) {
+        debug::label(gl::TEXTURE, self.id, label);
+        self.label = Some(label.into());
+    }
+
     /// Returns empty texture.
-    pub fn empty(settings: &TextureSettings) -> Result<Self, String> {
+    pub fn empty(settings: &TextureSettings) -> Result<Self, Error> {
         CreateTexture::create(&mut (), Format::Rgba8, &[0u8; 4], [1, 1], settings)
     }
 
     /// Loads image from memory, the format is 8-bit greyscale.
+    ///
+    /// The values are used as alpha, see `PixelFormat::A8`.
     pub fn from_memory_alpha(
         buf: &[u8],
         width: u32,
         height: u32,
         settings: &TextureSettings,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, Error> {
+        Texture::from_memory(buf, PixelFormat::A8, width, height, settings)
+    }
+
+    /// Loads image from memory in any pixel format.
+    ///
+    /// Rows are tightly packed, without padding.
+    pub fn from_memory(
+        buf: &[u8],
+        format: PixelFormat,
+        width: u32,
+        height: u32,
+        settings: &TextureSettings,
+    ) -> Result<Self, Error> {
+        if !gl::GenTextures::is_loaded() {
+            return Err(Error::ContextMissing);
+        }
         let size = [width, height];
-        let buffer = ops::alpha_to_rgba8(buf, size);
-        CreateTexture::create(&mut (), Format::Rgba8, &buffer, size, settings)
+        check_len(buf, format, size)?;
+        if USE_WEBGL && format == PixelFormat::Rgba16 {
+            return Err(Error::UnsupportedFormat(
+                "Rgba16 textures are not supported on WebGL".into(),
+            ));
+        }
+        let mut texture = if format.is_expanded() {
+            create(expand_rgba8(format, buf).as_ptr(), format, size, settings)
+        } else {
+            create(buf.as_ptr(), format, size, settings)
+        };
+        texture.format = format;
+        Ok(texture)
     }
 
     /// Loads image by relative file name to the asset root.
-    pub fn from_path<P>(path: P, settings: &TextureSettings) -> Result<Self, String>
+    pub fn from_path<P>(path: P, settings: &TextureSettings) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Texture::from_path_with_options(path, settings, &TextureLoadOptions::new())
+    }
+
+    /// Loads image by file name, transforming the pixels before upload.
+    pub fn from_path_with_options<P>(
+        path: P,
+        settings: &TextureSettings,
+        options: &TextureLoadOptions,
+    ) -> Result<Self, Error>
     where
         P: AsRef<Path>,
     {
         let path = path.as_ref();
 
-        let img = match image::open(path) {
-            Ok(img) => img,
-            Err(e) => {
-                return Err(format!(
-                    "Could not load '{:?}': {:?}",
-                    path.file_name().unwrap(),
-                    e
-                ))
-            }
-        };
+        let img = image::open(path).map_err(|source| Error::Image {
+            path: Some(path.to_path_buf()),
+            source,
+        })?;
 
-        let img = match img {
+        let mut img = match img {
             DynamicImage::ImageRgba8(img) => img,
             x => x.to_rgba8(),
         };
+        options.apply(&mut img);
+        check_size(&img)?;
 
         Ok(Texture::from_image(&img, settings))
     }
 
     /// Load image from bytes.
-    pub fn from_bytes(bytes: &[u8], settings: &TextureSettings) -> Result<Self, String> {
-        let img = match image::load_from_memory(bytes) {
-            Ok(img) => img,
-            Err(e) => return Err(format!("Could not load image from bytes. {:?}", e)),
-        };
+    pub fn from_bytes(bytes: &[u8], settings: &TextureSettings) -> Result<Self, Error> {
+        Texture::from_bytes_with_options(bytes, settings, &TextureLoadOptions::new())
+    }
+
+    /// Load image from bytes, transforming the pixels before upload.
+    pub fn from_bytes_with_options(
+        bytes: &[u8],
+        settings: &TextureSettings,
+        options: &TextureLoadOptions,
+    ) -> Result<Self, Error> {
+        let img =
+            image::load_from_memory(bytes).map_err(|source| Error::Image { path: None, source })?;
 
-        let img = match img {
+        let mut img = match img {
             DynamicImage::ImageRgba8(img) => img,
             x => x.to_rgba8(),
         };
+        options.apply(&mut img);
+        check_size(&img)?;
 
         Ok(Texture::from_image(&img, settings))
     }
 
+    /// Loads image with a precomputed mip chain from memory.
+    ///
+    /// The first level is the full size image,
+    /// each following level halves the size, rounding down to at least 1.
+    /// Levels are not generated again after updates.
+    pub fn from_mipmaps(
+        levels: &[&[u8]],
+        format: PixelFormat,
+        width: u32,
+        height: u32,
+        settings: &TextureSettings,
+    ) -> Result<Self, Error> {
+        if levels.is_empty() {
+            return Err(Error::Texture("Expected at least one mip level".into()));
+        }
+        check_levels(levels.len() as u32, width, height)?;
+        for (level, &memory) in levels.iter().enumerate().skip(1) {
+            check_len(memory, format, mip_size(width, height, level))?;
+        }
+        // Sample the supplied levels instead of generating them.
+        let filters = settings.generate_mipmap(levels.len() > 1);
+        let mut texture = Texture::from_memory(
+            levels[0],
+            format,
+            width,
+            height,
+            &filters.generate_mipmap(false),
+        )?;
+        let gl_format = gl_format(format, settings);
+        unsafe {
+            set_parameters(&filters);
+            gl_check!(TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAX_LEVEL,
+                levels.len() as i32 - 1,
+            ));
+            gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+        }
+        for (level, &memory) in levels.iter().enumerate().skip(1) {
+            let size = mip_size(width, height, level);
+            let expanded;
+            let pixels = if format.is_expanded() {
+                expanded = expand_rgba8(format, memory);
+                &expanded
+            } else {
+                memory
+            };
+            unsafe {
+                gl_check!(TexImage2D(
+                    gl::TEXTURE_2D,
+                    level as i32,
+                    gl_format.internal_format as i32,
+                    size[0] as i32,
+                    size[1] as i32,
+                    0,
+                    gl_format.format,
+                    gl_format.ty,
+                    pixels.as_ptr() as *const _,
+                ));
+            }
+        }
+        unsafe {
+            gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+        }
+        texture.max_level = Some(levels.len() as u32 - 1);
+        texture.settings = filters;
+        Ok(texture)
+    }
+
+    /// Loads a block compressed texture from a KTX2 or DDS container.
+    ///
+    /// All mip levels stored in the container are uploaded.
+    /// Color formats are stored as sRGB unless gamma is converted in shaders,
+    /// like uncompressed textures.
+    /// Returns an error if the context does not support the format.
+    pub fn from_compressed(bytes: &[u8], settings: &TextureSettings) -> Result<Self, Error> {
+        if !gl::GenTextures::is_loaded() {
+            return Err(Error::ContextMissing);
+        }
+        let image = compressed::parse(bytes)?;
+        if !image.format.is_supported() {
+            return Err(Error::UnsupportedFormat(format!(
+                "{:?} textures need one of the extensions {}",
+                image.format,
+                image.format.extensions().join(", ")
+            )));
+        }
+        let internal_format = image.format.gl_format(!settings.get_convert_gamma());
+        // Sample the stored levels instead of generating them.
+        let levels = image.levels.len();
+        let filters = settings.generate_mipmap(levels > 1);
+        let mut id: GLuint = 0;
+        unsafe {
+            gl_check!(GenTextures(1, &mut id));
+            gl_check!(BindTexture(gl::TEXTURE_2D, id));
+            set_parameters(&filters);
+            gl_check!(TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAX_LEVEL,
+                levels as i32 - 1,
+            ));
+            for (level, data) in image.levels.iter().enumerate() {
+                gl_check!(CompressedTexImage2D(
+                    gl::TEXTURE_2D,
+                    level as i32,
+                    internal_format,
+                    (image.width >> level).max(1) as i32,
+                    (image.height >> level).max(1) as i32,
+                    0,
+                    data.len() as i32,
+                    data.as_ptr() as *const _,
+                ));
+            }
+        }
+
+        let label = format!(
+            "Texture {}x{} {:?}",
+            image.width, image.height, image.format
+        );
+        debug::label(gl::TEXTURE, id, &label);
+        let mut texture = Texture::new(id, image.width, image.height);
+        texture.compressed = Some(image.format);
+        texture.settings = filters;
+        Ok(texture)
+    }
+
     /// Creates a texture from image.
     pub fn from_image(img: &RgbaImage, settings: &TextureSettings) -> Self {
         let (width, height) = img.dimensions();
         CreateTexture::create(&mut (), Format::Rgba8, img, [width, height], settings).unwrap()
     }
 
+    /// Creates a texture from image, transforming a copy of the pixels before upload.
+    pub fn from_image_with_options(
+        img: &RgbaImage,
+        settings: &TextureSettings,
+        options: &TextureLoadOptions,
+    ) -> Self {
+        let mut img = img.clone();
+        options.apply(&mut img);
+        Texture::from_image(&img, settings)
+    }
+
+    /// Creates a texture from image and keeps a copy of the data in memory.
+    ///
+    /// Such a texture can be re-uploaded with `restore`
+    /// after the OpenGL context was lost.
+    pub fn from_image_retained(img: &RgbaImage, settings: &TextureSettings) -> Self {
+        let mut texture = Texture::from_image(img, settings);
+        texture.retained = Some(Retained {
+            memory: img.as_raw().clone(),
+        });
+        texture
+    }
+
+    /// Returns `true` if the texture keeps a copy of its data in memory.
+    pub fn is_retained(&self) -> bool {
+        self.retained.is_some()
+    }
+
+    /// Re-uploads the retained data after the OpenGL context was restored.
+    ///
+    /// The old texture id belongs to the lost context and is not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh: Texture = match self.retained {
+            Some(ref retained) => Texture::from_memory(
+                &retained.memory,
+                self.format,
+                self.width,
+                self.height,
+                &self.settings,
+            )?,
+            None => return Err(Error::NotRetained),
+        };
+        self.id = fresh.id;
+        mem::forget(fresh);
+        self.reapply_state();
+        Ok(())
+    }
+
+    /// Replaces the contents with an image of any size,
+    /// keeping the settings, lod bias, max level, anisotropy and label.
+    ///
+    /// The texture gets a new id. Images above the size limit are rejected.
+    pub(crate) fn replace_image(&mut self, img: &RgbaImage) -> Result<(), Error> {
+        check_size(img)?;
+        let fresh = Texture::from_image(img, &self.settings);
+        if self.owned {
+            garbage::delete(Garbage::Texture(self.id));
+        }
+        self.id = fresh.id;
+        self.owned = true;
+        self.width = fresh.width;
+        self.height = fresh.height;
+        self.format = fresh.format;
+        self.compressed = None;
+        mem::forget(fresh);
+        if let Some(ref mut retained) = self.retained {
+            retained.memory = img.as_raw().clone();
+        }
+        self.reapply_state();
+        Ok(())
+    }
+
+    // Applies the state kept outside of the settings to a new texture id.
+    fn reapply_state(&mut self) {
+        if self.lod_bias != 0.0 {
+            self.set_lod_bias(self.lod_bias);
+        }
+        if self.max_level.is_some() {
+            self.set_max_level(self.max_level);
+        }
+        if self.anisotropy > 1.0 {
+            self.set_anisotropy(self.anisotropy);
+        }
+        if let Some(ref label) = self.label {
+            debug::label(gl::TEXTURE, self.id, label);
+        }
+    }
+
+    /// Allocates a texture without uploading any data.
+    pub(crate) fn allocate(size: [u32; 2], settings: &TextureSettings) -> Self {
+        create(ptr::null(), PixelFormat::Rgba8, size, settings)
+    }
+
+    /// Patches the retained memory, if any, after updating a region.
+    pub(crate) fn update_retained(&mut self, memory: &[u8], offset: [u32; 2], size: [u32; 2]) {
+        if let Some(ref mut retained) = self.retained {
+            let bpp = self.format.bytes_per_pixel();
+            let row = size[0] as usize * bpp;
+            for y in 0..size[1] as usize {
+                let src = y * row;
+                let dst =
+                    ((offset[1] as usize + y) * self.width as usize + offset[0] as usize) * bpp;
+                retained.memory[dst..dst + row].copy_from_slice(&memory[src..src + row]);
+            }
+        }
+    }
+
+    /// Updates a region of the texture from memory.
+    ///
+    /// The format must be the format the texture was created with.
+    pub fn update_memory(
+        &mut self,
+        format: PixelFormat,
+        memory: &[u8],
+        offset: [u32; 2],
+        size: [u32; 2],
+    ) -> Result<(), Error> {
+        if let Some(compressed) = self.compressed {
+            return Err(Error::UnsupportedFormat(format!(
+                "Can not update {:?} textures",
+                compressed
+            )));
+        }
+        if format != self.format {
+            return Err(Error::Texture(format!(
+                "Expected {:?} pixels, got {:?}",
+                self.format, format
+            )));
+        }
+        check_len(memory, format, size)?;
+        // Only the internal format depends on the settings.
+        let gl_format = gl_format(format, &TextureSettings::new());
+        let expanded;
+        let pixels = if format.is_expanded() {
+            expanded = expand_rgba8(format, memory);
+            &expanded
+        } else {
+            memory
+        };
+        let row = size[0] as usize * format.bytes_per_pixel();
+        // `is_multiple_of` needs a newer Rust than this crate supports.
+        #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+        let unaligned = row % 4 != 0;
+        unsafe {
+            gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+            if unaligned {
+                gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            }
+            gl_check!(TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                offset[0] as i32,
+                offset[1] as i32,
+                size[0] as i32,
+                size[1] as i32,
+                gl_format.format,
+                gl_format.ty,
+                pixels.as_ptr() as *const _,
+            ));
+            if unaligned {
+                gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+            }
+        }
+        self.update_retained(memory, offset, size);
+        if self.regenerate_mipmap {
+            self.generate_mipmap();
+        }
+        Ok(())
+    }
+
+    /// Reads the contents of the texture back into an image.
+    ///
+    /// Uses `GetTexImage` on desktop OpenGL,
+    /// and a temporary framebuffer on OpenGL ES and WebGL.
+    /// The framebuffer bound before the call is bound again afterwards.
+    /// The stored values are returned without conversion,
+    /// so sRGB textures give sRGB encoded pixels.
+    /// Wide formats are clamped to `0.0..=1.0`.
+    pub fn to_image(&self) -> Result<RgbaImage, Error> {
+        let (width, height) = (self.width, self.height);
+        let mut memory = vec![0u8; width as usize * height as usize * 4];
+        unsafe {
+            if !USE_WEBGL && gl::GetTexImage::is_loaded() {
+                gl_check!(BindTexture(gl::TEXTURE_2D, self.id));
+                gl_check!(GetTexImage(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    memory.as_mut_ptr() as *mut _,
+                ));
+            } else {
+                let mut bound = 0;
+                gl_check!(GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut bound));
+                let mut fbo = 0;
+                gl_check!(GenFramebuffers(1, &mut fbo));
+                gl_check!(BindFramebuffer(gl::FRAMEBUFFER, fbo));
+                gl_check!(FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    self.id,
+                    0,
+                ));
+                let status = gl_check!(CheckFramebufferStatus(gl::FRAMEBUFFER));
+                if status == gl::FRAMEBUFFER_COMPLETE {
+                    gl_check!(ReadPixels(
+                        0,
+                        0,
+                        width as i32,
+                        height as i32,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        memory.as_mut_ptr() as *mut _,
+                    ));
+                }
+                gl_check!(BindFramebuffer(gl::FRAMEBUFFER, bound as GLuint));
+                gl_check!(DeleteFramebuffers(1, &fbo));
+                if status != gl::FRAMEBUFFER_COMPLETE {
+                    let format = match self.compressed {
+                        Some(compressed) => format!("{:?}", compressed),
+                        None => format!("{:?}", self.format),
+                    };
+                    return Err(Error::Texture(format!(
+                        "Can not read back {} textures, framebuffer status {:#x}",
+                        format, status
+                    )));
+                }
+            }
+        }
+
+        // Swizzles only apply when sampling, so apply them here.
+        if !self.format.is_expanded() && self.compressed.is_none() {
+            for pixel in memory.chunks_exact_mut(4) {
+                let (r, g) = (pixel[0], pixel[1]);
+                match self.format {
+                    PixelFormat::A8 => pixel.copy_from_slice(&[255, 255, 255, r]),
+                    PixelFormat::L8 => pixel.copy_from_slice(&[r, r, r, 255]),
+                    PixelFormat::La8 => pixel.copy_from_slice(&[r, r, r, g]),
+                    _ => break,
+                }
+            }
+        }
+        Ok(RgbaImage::from_raw(width, height, memory).unwrap())
+    }
+
+    /// Saves the contents of the texture to an image file.
+    ///
+    /// The format is chosen from the extension, for example PNG.
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        self.to_image()?
+            .save(path)
+            .map_err(|e| Error::Texture(format!("Could not save '{:?}': {:?}", path, e)))
+    }
+
     /// Updates image with a new one.
     pub fn update(&mut self, img: &RgbaImage) {
         let (width, height) = img.dimensions();
@@ -173,9 +932,8 @@ impl Texture {
 
 impl Drop for Texture {
     fn drop(&mut self) {
-        unsafe {
-            let ids = [self.id];
-            gl::DeleteTextures(1, ids.as_ptr());
+        if self.owned {
+            garbage::delete(Garbage::Texture(self.id));
         }
     }
 }
@@ -187,102 +945,223 @@ impl ImageSize for Texture {
 }
 
 impl TextureOp<()> for Texture {
-    type Error = String;
+    type Error = Error;
 }
 
 impl CreateTexture<()> for Texture {
     fn create<S: Into<[u32; 2]>>(
         _factory: &mut (),
-        _format: Format,
+        format: Format,
         memory: &[u8],
         size: S,
         settings: &TextureSettings,
     ) -> Result<Self, Self::Error> {
         let size = size.into();
-        let mut id: GLuint = 0;
-        let internal_format = if settings.get_convert_gamma() {
-            gl::RGBA
-        } else {
-            gl::SRGB_ALPHA
-        };
-        unsafe {
-            gl::GenTextures(1, &mut id);
-            gl::BindTexture(gl::TEXTURE_2D, id);
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_MIN_FILTER,
-                settings.get_gl_min() as i32,
-            );
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_MAG_FILTER,
-                settings.get_gl_mag() as i32,
-            );
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_S,
-                settings.get_gl_wrap_u() as i32,
-            );
-            gl::TexParameteri(
-                gl::TEXTURE_2D,
-                gl::TEXTURE_WRAP_T,
-                settings.get_gl_wrap_v() as i32,
-            );
-            if settings.get_wrap_u() == Wrap::ClampToBorder
-                || settings.get_wrap_v() == Wrap::ClampToBorder
-            {
-                gl::TexParameterfv(
-                    gl::TEXTURE_2D,
-                    gl::TEXTURE_BORDER_COLOR,
-                    settings.get_border_color().as_ptr(),
-                );
-            }
-            if settings.get_generate_mipmap() {
-                gl::GenerateMipmap(gl::TEXTURE_2D);
+        Texture::from_memory(memory, format.into(), size[0], size[1], settings)
+    }
+}
+
+// Sets the filters and wrapping of the bound texture.
+unsafe fn set_parameters(settings: &TextureSettings) {
+    gl_check!(TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_MIN_FILTER,
+        settings.get_gl_min() as i32,
+    ));
+    gl_check!(TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_MAG_FILTER,
+        settings.get_gl_mag() as i32,
+    ));
+    gl_check!(TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_WRAP_S,
+        settings.get_gl_wrap_u() as i32,
+    ));
+    gl_check!(TexParameteri(
+        gl::TEXTURE_2D,
+        gl::TEXTURE_WRAP_T,
+        settings.get_gl_wrap_v() as i32,
+    ));
+    if settings.get_wrap_u() == Wrap::ClampToBorder || settings.get_wrap_v() == Wrap::ClampToBorder
+    {
+        gl_check!(TexParameterfv(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_BORDER_COLOR,
+            settings.get_border_color().as_ptr(),
+        ));
+    }
+}
+
+// Gets the settings a texture can switch to without re-uploading.
+fn changed_settings(
+    old: &TextureSettings,
+    new: &TextureSettings,
+    compressed: bool,
+) -> TextureSettings {
+    let mut settings = new
+        .convert_gamma(old.get_convert_gamma())
+        .compress(old.get_compress());
+    if compressed {
+        settings.set_generate_mipmap(old.get_generate_mipmap());
+    }
+    settings
+}
+
+// Checks that an image fits in a single texture.
+fn check_size(img: &RgbaImage) -> Result<(), Error> {
+    let (width, height) = img.dimensions();
+    check_dimensions(width, height)
+}
+
+// Checks that a size fits in a single texture.
+pub(crate) fn check_dimensions(width: u32, height: u32) -> Result<(), Error> {
+    if !gl::GenTextures::is_loaded() {
+        return Err(Error::ContextMissing);
+    }
+    let max = max_texture_size();
+    if width > max || height > max {
+        return Err(Error::Texture(format!(
+            "The image is {}x{} pixels, but textures are limited to {} pixels, use TiledTexture",
+            width, height, max
+        )));
+    }
+    Ok(())
+}
+
+// Checks that a mip chain has no more levels than halving the size allows.
+pub(crate) fn check_levels(count: u32, width: u32, height: u32) -> Result<(), Error> {
+    let max = 32 - width.max(height).max(1).leading_zeros();
+    if count > max {
+        return Err(Error::Texture(format!(
+            "{} mip levels are too many for {}x{} pixels",
+            count, width, height
+        )));
+    }
+    Ok(())
+}
+
+// Gets the size of a mip level, rounding down to at least 1.
+fn mip_size(width: u32, height: u32, level: usize) -> [u32; 2] {
+    [(width >> level).max(1), (height >> level).max(1)]
+}
+
+// Checks that memory holds enough tightly packed pixels.
+fn check_len(memory: &[u8], format: PixelFormat, size: [u32; 2]) -> Result<(), Error> {
+    let len = size[0] as usize * size[1] as usize * format.bytes_per_pixel();
+    if memory.len() < len {
+        return Err(Error::Texture(format!(
+            "Expected {} bytes for {}x{} {:?} pixels, got {}",
+            len,
+            size[0],
+            size[1],
+            format,
+            memory.len()
+        )));
+    }
+    Ok(())
+}
+
+// Creates a texture from a pointer to pixel data,
+// or an offset into the bound pixel unpack buffer.
+//
+// Formats expanded on WebGL must already be converted to RGBA.
+fn create(
+    pixels: *const u8,
+    format: PixelFormat,
+    size: [u32; 2],
+    settings: &TextureSettings,
+) -> Texture {
+    let mut id: GLuint = 0;
+    let gl_format = gl_format(format, settings);
+    let bpp = if format.is_expanded() {
+        4
+    } else {
+        format.bytes_per_pixel()
+    };
+    let row = size[0] as usize * bpp;
+    // `is_multiple_of` needs a newer Rust than this crate supports.
+    #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+    let unaligned = row % 4 != 0;
+    unsafe {
+        gl_check!(GenTextures(1, &mut id));
+        gl_check!(BindTexture(gl::TEXTURE_2D, id));
+        set_parameters(settings);
+        if let Some(swizzle) = gl_format.swizzle {
+            let parameters = [
+                gl::TEXTURE_SWIZZLE_R,
+                gl::TEXTURE_SWIZZLE_G,
+                gl::TEXTURE_SWIZZLE_B,
+                gl::TEXTURE_SWIZZLE_A,
+            ];
+            for (&parameter, &value) in parameters.iter().zip(swizzle.iter()) {
+                gl_check!(TexParameteri(gl::TEXTURE_2D, parameter, value as i32));
             }
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                internal_format as i32,
-                size[0] as i32,
-                size[1] as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                memory.as_ptr() as *const _,
-            );
         }
-
-        Ok(Texture::new(id, size[0], size[1]))
+        // Rows of narrow formats are not 4 byte aligned in general.
+        if unaligned {
+            gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+        }
+        gl_check!(TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl_format.internal_format as i32,
+            size[0] as i32,
+            size[1] as i32,
+            0,
+            gl_format.format,
+            gl_format.ty,
+            pixels as *const _,
+        ));
+        if unaligned {
+            gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+        }
+        // Build the levels from the uploaded image,
+        // or after the first update of an allocated texture.
+        if settings.get_generate_mipmap() && !pixels.is_null() {
+            gl_check!(GenerateMipmap(gl::TEXTURE_2D));
+        }
     }
+
+    debug::label(gl::TEXTURE, id, &format!("Texture {}x{}", size[0], size[1]));
+    let mut texture = Texture::new(id, size[0], size[1]);
+    texture.regenerate_mipmap = settings.get_generate_mipmap();
+    texture.settings = *settings;
+    texture
 }
 
 impl UpdateTexture<()> for Texture {
     fn update<O: Into<[u32; 2]>, S: Into<[u32; 2]>>(
         &mut self,
         _factory: &mut (),
-        _format: Format,
+        format: Format,
         memory: &[u8],
         offset: O,
         size: S,
     ) -> Result<(), Self::Error> {
-        let offset = offset.into();
-        let size = size.into();
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
-            gl::TexSubImage2D(
-                gl::TEXTURE_2D,
-                0,
-                offset[0] as i32,
-                offset[1] as i32,
-                size[0] as i32,
-                size[1] as i32,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                memory.as_ptr() as *const _,
-            );
-        }
-
-        Ok(())
+        self.update_memory(format.into(), memory, offset.into(), size.into())
     }
 }
+
+#[test]
+fn test_expand_rgba8() {
+    assert_eq!(expand_rgba8(PixelFormat::A8, &[7]), vec![255, 255, 255, 7]);
+    assert_eq!(expand_rgba8(PixelFormat::La8, &[1, 2]), vec![1, 1, 1, 2]);
+    assert_eq!(
+        expand_rgba8(PixelFormat::Bgra8, &[1, 2, 3, 4]),
+        vec![3, 2, 1, 4]
+    );
+}
+
+#[test]
+fn test_changed_settings() {
+    let old = TextureSettings::new().convert_gamma(true);
+    let new = TextureSettings::new().generate_mipmap(true);
+    let changed = changed_settings(&old, &new, false);
+    assert!(changed.get_generate_mipmap());
+    assert!(changed.get_convert_gamma());
+    // Compressed textures without levels can not switch to mipmaps.
+    let changed = changed_settings(&old, &new, true);
+    assert!(!changed.get_generate_mipmap());
+    assert_eq!(changed.get_gl_min(), gl::LINEAR);
+}
@@ -0,0 +1,178 @@
+//! Images split into several textures, for sizes above the texture size limit.
+
+use graphics::math::Matrix2d;
+use graphics::{DrawState, Graphics, Image};
+use image::{imageops, DynamicImage, RgbaImage};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::{ImageSize, Texture, TextureSettings};
+
+/// Gets the largest width and height of a texture supported by the context.
+pub fn max_texture_size() -> u32 {
+    let mut size = 0;
+    unsafe {
+        gl_check!(GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut size));
+    }
+    size.max(0) as u32
+}
+
+/// An image stored in a grid of textures, drawn as one image.
+///
+/// Each tile repeats one pixel of its neighbours at the shared edges,
+/// so linear filtering does not show seams between tiles.
+pub struct TiledTexture {
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+}
+
+struct Tile {
+    texture: Texture,
+    // The part of the image drawn from this tile, `[x, y, w, h]` in pixels.
+    area: [u32; 4],
+    // The position of the texture in the image, including the repeated edge.
+    origin: [u32; 2],
+}
+
+impl TiledTexture {
+    /// Splits an image into tiles of at most `max_texture_size` pixels.
+    pub fn from_image(img: &RgbaImage, settings: &TextureSettings) -> Result<Self, Error> {
+        if !gl::GenTextures::is_loaded() {
+            return Err(Error::ContextMissing);
+        }
+        TiledTexture::from_image_with_tile_size(img, max_texture_size(), settings)
+    }
+
+    /// Splits an image into tiles of at most `tile_size` pixels.
+    ///
+    /// Returns an error if the image needs several tiles and `tile_size` is less than `3`,
+    /// which leaves no room for the repeated edges.
+    pub fn from_image_with_tile_size(
+        img: &RgbaImage,
+        tile_size: u32,
+        settings: &TextureSettings,
+    ) -> Result<Self, Error> {
+        let (width, height) = img.dimensions();
+        let columns = tile_ranges(width, tile_size)?;
+        let rows = tile_ranges(height, tile_size)?;
+        let mut tiles = vec![];
+        for &(y, h) in &rows {
+            for &(x, w) in &columns {
+                let x0 = x.saturating_sub(1);
+                let y0 = y.saturating_sub(1);
+                let x1 = (x + w + 1).min(width);
+                let y1 = (y + h + 1).min(height);
+                let sub = imageops::crop_imm(img, x0, y0, x1 - x0, y1 - y0).to_image();
+                tiles.push(Tile {
+                    texture: Texture::from_image(&sub, settings),
+                    area: [x, y, w, h],
+                    origin: [x0, y0],
+                });
+            }
+        }
+        Ok(TiledTexture {
+            width,
+            height,
+            tiles,
+        })
+    }
+
+    /// Loads an image by file name and splits it into tiles.
+    pub fn from_path<P>(path: P, settings: &TextureSettings) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let img = image::open(path).map_err(|source| Error::Image {
+            path: Some(path.to_path_buf()),
+            source,
+        })?;
+        let img = match img {
+            DynamicImage::ImageRgba8(img) => img,
+            x => x.to_rgba8(),
+        };
+        TiledTexture::from_image(&img, settings)
+    }
+
+    /// Gets the number of textures.
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Draws the tiles covering the source rectangle of an image.
+    ///
+    /// Source rectangles are in pixels of the whole image, and may span many tiles.
+    pub fn draw<G>(&self, image: &Image, draw_state: &DrawState, transform: Matrix2d, g: &mut G)
+    where
+        G: Graphics<Texture = Texture>,
+    {
+        let [sx, sy, sw, sh] =
+            image
+                .source_rectangle
+                .unwrap_or([0.0, 0.0, self.width as f64, self.height as f64]);
+        let [rx, ry, rw, rh] = image.rectangle.unwrap_or([0.0, 0.0, sw, sh]);
+        if sw == 0.0 || sh == 0.0 {
+            return;
+        }
+        // Maps a source position to the rectangle, also for flipped source rectangles.
+        let map_x = |u: f64| rx + (u - sx) / sw * rw;
+        let map_y = |v: f64| ry + (v - sy) / sh * rh;
+        let (u0, u1) = (sx.min(sx + sw), sx.max(sx + sw));
+        let (v0, v1) = (sy.min(sy + sh), sy.max(sy + sh));
+        for tile in &self.tiles {
+            let [x, y, w, h] = tile.area;
+            let a = u0.max(x as f64);
+            let b = u1.min((x + w) as f64);
+            let c = v0.max(y as f64);
+            let d = v1.min((y + h) as f64);
+            if a >= b || c >= d {
+                continue;
+            }
+            let rect = [map_x(a), map_y(c), map_x(b) - map_x(a), map_y(d) - map_y(c)];
+            let src_rect = [
+                a - tile.origin[0] as f64,
+                c - tile.origin[1] as f64,
+                b - a,
+                d - c,
+            ];
+            Image {
+                rectangle: Some(rect),
+                source_rectangle: Some(src_rect),
+                ..*image
+            }
+            .draw(&tile.texture, draw_state, transform, g);
+        }
+    }
+}
+
+impl ImageSize for TiledTexture {
+    fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+// Splits a length into `(start, len)` parts,
+// which fit in a tile together with the repeated edges.
+fn tile_ranges(len: u32, tile_size: u32) -> Result<Vec<(u32, u32)>, Error> {
+    if len <= tile_size {
+        return Ok(vec![(0, len)]);
+    }
+    if tile_size < 3 {
+        return Err(Error::Texture(format!(
+            "Can not split {} pixels into tiles of {} pixels",
+            len, tile_size
+        )));
+    }
+    let step = tile_size - 2;
+    Ok((0..len.div_ceil(step))
+        .map(|i| (i * step, step.min(len - i * step)))
+        .collect())
+}
+
+#[test]
+fn test_tile_ranges() {
+    assert_eq!(tile_ranges(100, 4096).unwrap(), vec![(0, 100)]);
+    assert_eq!(tile_ranges(10, 6).unwrap(), vec![(0, 4), (4, 4), (8, 2)]);
+    assert!(tile_ranges(10, 0).is_err());
+}
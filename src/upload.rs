@@ -0,0 +1,243 @@
+//! Asynchronous texture uploads through pixel buffer objects.
+
+use gl::types::{GLintptr, GLsizeiptr, GLsync, GLuint};
+use std::{ptr, slice};
+
+use crate::debug;
+use crate::error::Error;
+use crate::garbage::{self, Garbage};
+use crate::{PixelFormat, Texture, TextureSettings};
+
+/// Streams texture data through a ring of pixel unpack buffers.
+///
+/// The data is written into mapped buffer memory and copied into the texture
+/// on the GPU, so the render thread does not wait for the transfer.
+/// Each buffer is guarded by a fence; a buffer still in use by the GPU
+/// gets fresh storage instead of blocking.
+pub struct TextureUploader {
+    slots: Vec<Slot>,
+    next: usize,
+}
+
+struct Slot {
+    pbo: GLuint,
+    capacity: usize,
+    fence: Option<GLsync>,
+}
+
+impl Drop for TextureUploader {
+    fn drop(&mut self) {
+        for slot in &self.slots {
+            if let Some(fence) = slot.fence {
+                garbage::delete(Garbage::Sync(fence as usize));
+            }
+            garbage::delete(Garbage::Buffer(slot.pbo));
+        }
+    }
+}
+
+impl TextureUploader {
+    /// Creates an uploader cycling through `count` buffers.
+    ///
+    /// Two or three buffers are usually enough for streaming one image per frame.
+    pub fn new(count: usize) -> Self {
+        TextureUploader {
+            slots: (0..count.max(1))
+                .map(|_| Slot {
+                    pbo: 0,
+                    capacity: 0,
+                    fence: None,
+                })
+                .collect(),
+            next: 0,
+        }
+    }
+
+    /// Creates a texture from RGBA data, uploading it through a buffer.
+    pub fn create(
+        &mut self,
+        memory: &[u8],
+        size: [u32; 2],
+        settings: &TextureSettings,
+    ) -> Result<Texture, Error> {
+        let mut texture = Texture::allocate(size, settings);
+        self.update(&mut texture, memory, [0, 0], size)?;
+        Ok(texture)
+    }
+
+    /// Updates a region of a texture with RGBA data.
+    pub fn update(
+        &mut self,
+        texture: &mut Texture,
+        memory: &[u8],
+        offset: [u32; 2],
+        size: [u32; 2],
+    ) -> Result<(), Error> {
+        if texture.get_format() != PixelFormat::Rgba8 || texture.get_compressed_format().is_some() {
+            return Err(Error::Texture(format!(
+                "Expected an Rgba8 texture, got {:?}",
+                texture.get_format()
+            )));
+        }
+        let len = size[0] as usize * size[1] as usize * 4;
+        if memory.len() < len {
+            return Err(Error::Texture(format!(
+                "Expected {} bytes for {}x{} pixels, got {}",
+                len,
+                size[0],
+                size[1],
+                memory.len()
+            )));
+        }
+        self.upload(texture.get_id(), offset, size, |buf| {
+            buf.copy_from_slice(&memory[..len])
+        })?;
+        texture.update_retained(memory, offset, size);
+        if texture.get_regenerate_mipmap() {
+            texture.generate_mipmap();
+        }
+        Ok(())
+    }
+
+    /// Updates a region of a texture, writing RGBA data directly into buffer memory.
+    ///
+    /// This avoids an extra copy, for example when decoding video frames.
+    pub fn update_with<F>(
+        &mut self,
+        texture: &mut Texture,
+        offset: [u32; 2],
+        size: [u32; 2],
+        f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(&mut [u8]),
+    {
+        if texture.is_retained() {
+            // Mapped memory is slow to read back, so write into a copy first.
+            let mut memory = vec![0; size[0] as usize * size[1] as usize * 4];
+            f(&mut memory);
+            return self.update(texture, &memory, offset, size);
+        }
+        self.upload(texture.get_id(), offset, size, f)?;
+        if texture.get_regenerate_mipmap() {
+            texture.generate_mipmap();
+        }
+        Ok(())
+    }
+
+    /// Forgets the buffers after the OpenGL context was lost.
+    ///
+    /// New buffers are created on the next upload.
+    pub fn restore(&mut self) {
+        for slot in &mut self.slots {
+            slot.pbo = 0;
+            slot.capacity = 0;
+            slot.fence = None;
+        }
+    }
+
+    fn upload<F>(&mut self, id: GLuint, offset: [u32; 2], size: [u32; 2], f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut [u8]),
+    {
+        let len = size[0] as usize * size[1] as usize * 4;
+        let index = self.next;
+        self.next = (self.next + 1) % self.slots.len();
+        let slot = &mut self.slots[index];
+        unsafe {
+            if slot.pbo == 0 {
+                gl_check!(GenBuffers(1, &mut slot.pbo));
+                debug::label(gl::BUFFER, slot.pbo, "TextureUploader");
+            }
+            gl_check!(BindBuffer(gl::PIXEL_UNPACK_BUFFER, slot.pbo));
+
+            // Check without waiting whether the GPU is done with the previous upload.
+            let busy = match slot.fence.take() {
+                Some(fence) => {
+                    let status = gl_check!(ClientWaitSync(fence, 0, 0));
+                    gl_check!(DeleteSync(fence));
+                    status == gl::TIMEOUT_EXPIRED || status == gl::WAIT_FAILED
+                }
+                None => false,
+            };
+            if let Some(capacity) = orphan_capacity(slot.capacity, len, busy) {
+                // Orphan the old storage, the driver frees it once the GPU is done.
+                gl_check!(BufferData(
+                    gl::PIXEL_UNPACK_BUFFER,
+                    capacity as GLsizeiptr,
+                    ptr::null(),
+                    gl::STREAM_DRAW,
+                ));
+                slot.capacity = capacity;
+            }
+
+            if gl::MapBufferRange::is_loaded() {
+                let access = gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT;
+                let mapped = gl_check!(MapBufferRange(
+                    gl::PIXEL_UNPACK_BUFFER,
+                    0,
+                    len as GLsizeiptr,
+                    access,
+                ));
+                if mapped.is_null() {
+                    gl_check!(BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0));
+                    return Err(Error::Texture("Could not map pixel buffer".into()));
+                }
+                f(slice::from_raw_parts_mut(mapped as *mut u8, len));
+                gl_check!(UnmapBuffer(gl::PIXEL_UNPACK_BUFFER));
+            } else {
+                let mut memory = vec![0u8; len];
+                f(&mut memory);
+                gl_check!(BufferSubData(
+                    gl::PIXEL_UNPACK_BUFFER,
+                    0 as GLintptr,
+                    len as GLsizeiptr,
+                    memory.as_ptr() as *const _,
+                ));
+            }
+
+            // The pixel pointer is an offset into the bound buffer.
+            gl_check!(BindTexture(gl::TEXTURE_2D, id));
+            gl_check!(TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                offset[0] as i32,
+                offset[1] as i32,
+                size[0] as i32,
+                size[1] as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            ));
+            gl_check!(BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0));
+            slot.fence = Some(gl_check!(FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)));
+        }
+        Ok(())
+    }
+}
+
+// Gets the size of fresh storage for an upload of `len` bytes,
+// or `None` when the current storage can be written.
+//
+// Fresh storage keeps the largest size, so alternating sizes do not reallocate.
+fn orphan_capacity(capacity: usize, len: usize, busy: bool) -> Option<usize> {
+    if busy || capacity < len {
+        Some(capacity.max(len))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_orphan_capacity() {
+    let mut capacity = 0;
+    for &(len, busy) in &[(1000, false), (10, true), (1000, false), (2000, true)] {
+        if let Some(fresh) = orphan_capacity(capacity, len, busy) {
+            capacity = fresh;
+        }
+        assert!(capacity >= len);
+    }
+    assert_eq!(capacity, 2000);
+    assert_eq!(orphan_capacity(1000, 10, false), None);
+    assert_eq!(orphan_capacity(1000, 10, true), Some(1000));
+}
@@ -0,0 +1,524 @@
+//! Video frames in planar YUV formats, converted to RGB on the GPU.
+
+// External crates.
+use gl::types::{GLint, GLuint};
+use graphics::math::Matrix2d;
+use graphics::BACK_END_MAX_VERTEX_COUNT as BUFFER_SIZE;
+use graphics::{triangulation, DrawState};
+use shader_version::glsl::GLSL;
+use shader_version::Shaders;
+use std::ffi::CString;
+use std::mem;
+
+// Local crate.
+use crate::back_end::USE_WEBGL;
+use crate::debug;
+use crate::error::Error;
+use crate::garbage::{self, Garbage};
+use crate::shader_utils::{compile_shader, link_program, uniform_location, DynamicAttribute};
+use crate::GlGraphics;
+
+// The number of chunks to fill up before rendering.
+const CHUNKS: usize = 10;
+
+/// The layout of the planes of a video frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvLayout {
+    /// A full size Y plane followed by quarter size U and V planes.
+    I420,
+    /// A full size Y plane followed by a quarter size plane of interleaved U and V.
+    Nv12,
+}
+
+/// The standard used to encode the colors of a video.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvColorSpace {
+    /// ITU-R BT.601, used for standard definition video.
+    Bt601,
+    /// ITU-R BT.709, used for high definition video.
+    Bt709,
+}
+
+/// The range of values used by the samples of a video.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum YuvRange {
+    /// Luma in `16..=235` and chroma in `16..=240`, common for video.
+    Limited,
+    /// All values in `0..=255`, common for images.
+    Full,
+}
+
+/// A video frame stored as one single-channel texture per plane.
+///
+/// The conversion to RGB happens in a shader when drawing,
+/// see `GlGraphics::tri_list_yuv`.
+pub struct VideoTexture {
+    planes: [GLuint; 3],
+    width: u32,
+    height: u32,
+    layout: YuvLayout,
+    color_space: YuvColorSpace,
+    range: YuvRange,
+}
+
+impl Drop for VideoTexture {
+    fn drop(&mut self) {
+        for &id in &self.planes[..self.plane_count()] {
+            garbage::delete(Garbage::Texture(id));
+        }
+    }
+}
+
+impl VideoTexture {
+    /// Creates a video texture of a size in pixels, with black contents.
+    pub fn new(
+        layout: YuvLayout,
+        color_space: YuvColorSpace,
+        range: YuvRange,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut video = VideoTexture {
+            planes: [0; 3],
+            width,
+            height,
+            layout,
+            color_space,
+            range,
+        };
+        video.allocate();
+        video
+    }
+
+    /// Gets the size of the video in pixels.
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Gets the layout of the planes.
+    pub fn get_layout(&self) -> YuvLayout {
+        self.layout
+    }
+
+    /// Sets the standard used to decode colors.
+    pub fn set_color_space(&mut self, color_space: YuvColorSpace) {
+        self.color_space = color_space;
+    }
+
+    /// Gets the standard used to decode colors.
+    pub fn get_color_space(&self) -> YuvColorSpace {
+        self.color_space
+    }
+
+    /// Sets the range of the samples.
+    pub fn set_range(&mut self, range: YuvRange) {
+        self.range = range;
+    }
+
+    /// Gets the range of the samples.
+    pub fn get_range(&self) -> YuvRange {
+        self.range
+    }
+
+    /// Uploads a frame.
+    ///
+    /// Takes three planes for `I420` and two planes for `Nv12`,
+    /// each with its stride, the number of bytes per row.
+    pub fn update(&mut self, planes: &[&[u8]], strides: &[usize]) -> Result<(), Error> {
+        let count = self.plane_count();
+        if planes.len() != count || strides.len() != count {
+            return Err(Error::Texture(format!(
+                "Expected {} planes for {:?}, got {}",
+                count,
+                self.layout,
+                planes.len()
+            )));
+        }
+        for i in 0..count {
+            let (width, height, format) = self.plane_format(i);
+            let channels = if format == gl::RG { 2 } else { 1 };
+            let row = width as usize * channels;
+            if strides[i] < row
+                || planes[i].len() < strides[i] * (height as usize).saturating_sub(1) + row
+            {
+                return Err(Error::Texture(format!(
+                    "Plane {} is too small for {}x{} samples",
+                    i, width, height
+                )));
+            }
+        }
+
+        unsafe {
+            // Rows of single-channel planes are not 4 byte aligned in general.
+            gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+            for i in 0..count {
+                let (width, height, format) = self.plane_format(i);
+                let channels = if format == gl::RG { 2 } else { 1 };
+                gl_check!(PixelStorei(
+                    gl::UNPACK_ROW_LENGTH,
+                    (strides[i] / channels) as i32
+                ));
+                gl_check!(BindTexture(gl::TEXTURE_2D, self.planes[i]));
+                gl_check!(TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    0,
+                    0,
+                    width as i32,
+                    height as i32,
+                    format,
+                    gl::UNSIGNED_BYTE,
+                    planes[i].as_ptr() as *const _,
+                ));
+            }
+            gl_check!(PixelStorei(gl::UNPACK_ROW_LENGTH, 0));
+            gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+        }
+        Ok(())
+    }
+
+    /// Draws the video inside a rectangle.
+    pub fn draw(
+        &self,
+        rect: [f64; 4],
+        draw_state: &DrawState,
+        transform: Matrix2d,
+        g: &mut GlGraphics,
+    ) {
+        let xy = triangulation::rect_tri_list_xy(transform, rect);
+        let uv = [
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [0.0, 1.0],
+            [1.0, 0.0],
+            [1.0, 1.0],
+            [0.0, 1.0],
+        ];
+        g.tri_list_yuv(draw_state, self, |f| f(&xy, &uv));
+    }
+
+    /// Recreates the planes after the OpenGL context was restored.
+    ///
+    /// The old textures belong to the lost context and are not deleted.
+    /// The contents are black until the next `update`.
+    pub fn restore(&mut self) {
+        self.allocate();
+    }
+
+    fn plane_count(&self) -> usize {
+        match self.layout {
+            YuvLayout::I420 => 3,
+            YuvLayout::Nv12 => 2,
+        }
+    }
+
+    // Returns the size and pixel format of a plane.
+    fn plane_format(&self, plane: usize) -> (u32, u32, GLuint) {
+        let (w, h) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        match (self.layout, plane) {
+            (_, 0) => (self.width, self.height, gl::RED),
+            (YuvLayout::Nv12, _) => (w, h, gl::RG),
+            (YuvLayout::I420, _) => (w, h, gl::RED),
+        }
+    }
+
+    fn allocate(&mut self) {
+        let count = self.plane_count();
+        for i in 0..count {
+            let (width, height, format) = self.plane_format(i);
+            let (internal_format, black) = if format == gl::RG {
+                (gl::RG8, vec![128; width as usize * height as usize * 2])
+            } else if i == 0 {
+                (gl::R8, vec![0; width as usize * height as usize])
+            } else {
+                (gl::R8, vec![128; width as usize * height as usize])
+            };
+            let mut id = 0;
+            unsafe {
+                gl_check!(GenTextures(1, &mut id));
+                gl_check!(BindTexture(gl::TEXTURE_2D, id));
+                for &(parameter, value) in &[
+                    (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                    (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+                    (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                    (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+                ] {
+                    gl_check!(TexParameteri(gl::TEXTURE_2D, parameter, value as i32));
+                }
+                gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 1));
+                gl_check!(TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    internal_format as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    format,
+                    gl::UNSIGNED_BYTE,
+                    black.as_ptr() as *const _,
+                ));
+                gl_check!(PixelStorei(gl::UNPACK_ALIGNMENT, 4));
+            }
+            debug::label(gl::TEXTURE, id, &format!("VideoTexture plane {}", i));
+            self.planes[i] = id;
+        }
+    }
+
+    pub(crate) fn state(&self) -> YuvState {
+        let (matrix, offset) = yuv_matrix(self.color_space, self.range);
+        let planes = match self.layout {
+            YuvLayout::I420 => self.planes,
+            // Bind the chroma plane twice, so every sampler has a texture.
+            YuvLayout::Nv12 => [self.planes[0], self.planes[1], self.planes[1]],
+        };
+        YuvState {
+            planes,
+            nv12: (self.layout == YuvLayout::Nv12) as GLint,
+            matrix,
+            offset,
+        }
+    }
+}
+
+// Computes the matrix converting YUV to RGB as column major 3x3 matrix,
+// and the offset to subtract from YUV samples in `0.0..=1.0` first.
+fn yuv_matrix(color_space: YuvColorSpace, range: YuvRange) -> ([f32; 9], [f32; 3]) {
+    let (kr, kb) = match color_space {
+        YuvColorSpace::Bt601 => (0.299, 0.114),
+        YuvColorSpace::Bt709 => (0.2126, 0.0722),
+    };
+    let kg = 1.0 - kr - kb;
+    let (y_scale, c_scale, y_offset) = match range {
+        YuvRange::Limited => (255.0 / 219.0, 255.0 / 224.0, 16.0 / 255.0),
+        YuvRange::Full => (1.0, 1.0, 0.0),
+    };
+    let matrix = [
+        // Column of Y.
+        y_scale,
+        y_scale,
+        y_scale,
+        // Column of U.
+        0.0,
+        -2.0 * kb * (1.0 - kb) / kg * c_scale,
+        2.0 * (1.0 - kb) * c_scale,
+        // Column of V.
+        2.0 * (1.0 - kr) * c_scale,
+        -2.0 * kr * (1.0 - kr) / kg * c_scale,
+        0.0,
+    ];
+    (matrix, [y_offset, 128.0 / 255.0, 128.0 / 255.0])
+}
+
+/// The uniform values of a video draw call.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct YuvState {
+    planes: [GLuint; 3],
+    nv12: GLint,
+    matrix: [f32; 9],
+    offset: [f32; 3],
+}
+
+/// Describes how to render video textures.
+pub(crate) struct Yuv {
+    vertex_shader: GLuint,
+    fragment_shader: GLuint,
+    vertex_source: String,
+    fragment_source: String,
+    pub(crate) program: GLuint,
+    vao: GLuint,
+    samplers: [GLint; 3],
+    nv12: GLint,
+    matrix: GLint,
+    yuv_offset: GLint,
+    pos: DynamicAttribute,
+    uv: DynamicAttribute,
+    pub(crate) pos_buffer: Vec<[f32; 2]>,
+    pub(crate) uv_buffer: Vec<[f32; 2]>,
+    pub(crate) offset: usize,
+    pub(crate) last_state: Option<YuvState>,
+}
+
+impl Drop for Yuv {
+    fn drop(&mut self) {
+        garbage::delete(Garbage::VertexArray(self.vao));
+        garbage::delete(Garbage::Program(self.program));
+        garbage::delete(Garbage::Shader(self.vertex_shader));
+        garbage::delete(Garbage::Shader(self.fragment_shader));
+    }
+}
+
+impl Yuv {
+    /// Generate using the built-in YUV conversion shaders.
+    ///
+    /// # Panics
+    /// If the built-in shaders fail to compile
+    pub fn new(glsl: GLSL) -> Self {
+        let mut vertex_shaders = Shaders::new();
+        if USE_WEBGL {
+            vertex_shaders
+                .set(GLSL::V1_20, include_str!("shaders/yuv/120_webgl.glslv"))
+                .set(
+                    GLSL::V1_50,
+                    include_str!("shaders/yuv/150_core_webgl.glslv"),
+                )
+        } else {
+            vertex_shaders
+                .set(GLSL::V1_20, include_str!("shaders/yuv/120.glslv"))
+                .set(GLSL::V1_50, include_str!("shaders/yuv/150_core.glslv"))
+        };
+
+        let mut fragment_shaders = Shaders::new();
+        if USE_WEBGL {
+            fragment_shaders
+                .set(GLSL::V1_20, include_str!("shaders/yuv/120_webgl.glslf"))
+                .set(
+                    GLSL::V1_50,
+                    include_str!("shaders/yuv/150_core_webgl.glslf"),
+                )
+        } else {
+            fragment_shaders
+                .set(GLSL::V1_20, include_str!("shaders/yuv/120.glslf"))
+                .set(GLSL::V1_50, include_str!("shaders/yuv/150_core.glslf"))
+        };
+
+        let v_shader = vertex_shaders.get(glsl).unwrap();
+        let f_shader = fragment_shaders.get(glsl).unwrap();
+        Yuv::from_sources(v_shader, f_shader).unwrap()
+    }
+
+    /// Recreates the OpenGL objects after the context was lost.
+    ///
+    /// The old objects belong to the lost context and are not deleted.
+    pub fn restore(&mut self) -> Result<(), Error> {
+        let fresh = Yuv::from_sources(&self.vertex_source, &self.fragment_source)?;
+        mem::replace(self, fresh).invalidate();
+        Ok(())
+    }
+
+    fn from_sources(v_shader: &str, f_shader: &str) -> Result<Self, Error> {
+        let v_shader_compiled = compile_shader(gl::VERTEX_SHADER, v_shader)?;
+
+        let f_shader_compiled = compile_shader(gl::FRAGMENT_SHADER, f_shader)?;
+
+        let program;
+        unsafe {
+            program = gl_check!(CreateProgram());
+            gl_check!(AttachShader(program, v_shader_compiled));
+            gl_check!(AttachShader(program, f_shader_compiled));
+
+            let c_o_color = CString::new("o_Color").unwrap();
+            if !USE_WEBGL {
+                gl_check!(BindFragDataLocation(program, 0, c_o_color.as_ptr()));
+            }
+            drop(c_o_color);
+        }
+
+        let mut vao = 0;
+        unsafe {
+            gl_check!(GenVertexArrays(1, &mut vao));
+        }
+        link_program(program)?;
+        let pos = DynamicAttribute::xy(program, "pos", vao)?;
+        let uv = DynamicAttribute::uv(program, "uv", vao)?;
+        let samplers = [
+            uniform_location(program, "s_y")? as GLint,
+            uniform_location(program, "s_u")? as GLint,
+            uniform_location(program, "s_v")? as GLint,
+        ];
+        let nv12 = uniform_location(program, "nv12")? as GLint;
+        let matrix = uniform_location(program, "yuv_matrix")? as GLint;
+        let yuv_offset = uniform_location(program, "yuv_offset")? as GLint;
+        debug::label(gl::PROGRAM, program, "Yuv");
+        debug::label(gl::VERTEX_ARRAY, vao, "Yuv");
+        pos.label("Yuv pos");
+        uv.label("Yuv uv");
+        Ok(Yuv {
+            vao,
+            vertex_shader: v_shader_compiled,
+            fragment_shader: f_shader_compiled,
+            vertex_source: v_shader.to_string(),
+            fragment_source: f_shader.to_string(),
+            program,
+            samplers,
+            nv12,
+            matrix,
+            yuv_offset,
+            pos,
+            uv,
+            pos_buffer: vec![[0.0; 2]; CHUNKS * BUFFER_SIZE],
+            uv_buffer: vec![[0.0; 2]; CHUNKS * BUFFER_SIZE],
+            offset: 0,
+            last_state: None,
+        })
+    }
+
+    // Forgets the OpenGL objects without deleting them.
+    fn invalidate(&mut self) {
+        self.vao = 0;
+        self.program = 0;
+        self.vertex_shader = 0;
+        self.fragment_shader = 0;
+        self.pos.invalidate();
+        self.uv.invalidate();
+    }
+
+    pub(crate) fn flush(&mut self) {
+        if let Some(state) = self.last_state {
+            unsafe {
+                gl_check!(BindVertexArray(self.vao));
+                for (i, (&sampler, &plane)) in
+                    self.samplers.iter().zip(state.planes.iter()).enumerate()
+                {
+                    gl_check!(ActiveTexture(gl::TEXTURE0 + i as u32));
+                    gl_check!(BindTexture(gl::TEXTURE_2D, plane));
+                    gl_check!(Uniform1i(sampler, i as i32));
+                }
+                // Other pipelines use the first texture unit.
+                gl_check!(ActiveTexture(gl::TEXTURE0));
+                gl_check!(Uniform1i(self.nv12, state.nv12));
+                gl_check!(UniformMatrix3fv(
+                    self.matrix,
+                    1,
+                    gl::FALSE,
+                    state.matrix.as_ptr()
+                ));
+                let o = state.offset;
+                gl_check!(Uniform3f(self.yuv_offset, o[0], o[1], o[2]));
+                // Render triangles whether they are facing
+                // clockwise or counter clockwise.
+                gl_check!(Disable(gl::CULL_FACE));
+                self.pos.set(&self.pos_buffer[..self.offset]);
+                self.uv.set(&self.uv_buffer[..self.offset]);
+                gl_check!(DrawArrays(gl::TRIANGLES, 0, self.offset as i32));
+                gl_check!(BindVertexArray(0));
+            }
+        }
+
+        self.offset = 0;
+    }
+}
+
+#[test]
+fn test_yuv_matrix() {
+    let convert = |matrix: [f32; 9], offset: [f32; 3], yuv: [f32; 3]| {
+        let v = [yuv[0] - offset[0], yuv[1] - offset[1], yuv[2] - offset[2]];
+        [
+            matrix[0] * v[0] + matrix[3] * v[1] + matrix[6] * v[2],
+            matrix[1] * v[0] + matrix[4] * v[1] + matrix[7] * v[2],
+            matrix[2] * v[0] + matrix[5] * v[1] + matrix[8] * v[2],
+        ]
+    };
+    let gray = 128.0 / 255.0;
+    for &color_space in &[YuvColorSpace::Bt601, YuvColorSpace::Bt709] {
+        let (matrix, offset) = yuv_matrix(color_space, YuvRange::Limited);
+        let white = convert(matrix, offset, [235.0 / 255.0, gray, gray]);
+        for c in &white {
+            assert!((c - 1.0).abs() < 1e-5);
+        }
+        let (matrix, offset) = yuv_matrix(color_space, YuvRange::Full);
+        let black = convert(matrix, offset, [0.0, gray, gray]);
+        for c in &black {
+            assert!(c.abs() < 1e-5);
+        }
+    }
+}